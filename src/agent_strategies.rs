@@ -1,7 +1,16 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
 use crate::hopcroft_karp::HopcroftKarp;
+use crate::hungarian::Hungarian;
 use crate::map::*;
-use crate::matching::{Matcher, makespan_solve};
-use crate::flow::MaxFlow;
+use crate::matching::{Bottleneck, Matcher, WeightedMatcher, bottleneck_solve, makespan_edges};
+use crate::flow::{MaxFlow, MinCostFlow};
+use crate::game::GameSolver;
+use crate::observation::Observation;
+use crate::target_strategies::{MotionState, TransitionModel};
+use crate::time_expanded::{Node, TimeExpanded};
 
 #[allow(dead_code)]
 #[derive(PartialEq, Debug, Clone)]
@@ -11,44 +20,222 @@ pub enum AgentStrategies {
     CollisionAssigned,
     CollisionFree,
     NoCollisionFree,
+    Pibt,
+    Optimal,
+    ExpectedCapture,
+}
+
+// what the assignment of agents to targets optimises
+#[allow(dead_code)]
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Objective {
+    Makespan,    // latest capture time
+    SumOfCosts,  // sum of capture times (same order as the average)
+    TotalTravel, // sum of moves made by agents
 }
 
 pub trait AgentStrategy {
     fn pick(&mut self, map: &Map, agents: &mut Vec<Agent>, targets: &Vec<Target>) -> Vec<Direction>;
+
+    // every agent can pick on what it alone knows, others only get shared observations (see Runner::check)
+    fn observes_alone(&self) -> bool { false }
+
+    // pick under partial observability (see Runner::observation), one joint pick per turn on
+    // the targets known to the team; strategies planning jointly or keeping state between picks
+    // can not let every agent decide on its own knowledge
+    fn pick_observed(&mut self, map: &Map, agents: &mut Vec<Agent>, observation: &Observation) -> Vec<Direction> {
+        debug_assert!(observation.shared || self.observes_alone());
+        self.pick(map, agents, &observation.team())
+    }
+}
+
+// every agent moves as pick decides on what it knows, changes to the other agents are dropped
+fn pick_each(agents: &mut Vec<Agent>, observation: &Observation,
+             mut pick: impl FnMut(&mut Vec<Agent>, &Vec<Target>) -> Vec<Direction>) -> Vec<Direction> {
+    let mut res = vec![Direction::None; agents.len()];
+    let mut updated = agents.clone();
+    for idx in 0..agents.len() {
+        let mut view = agents.clone();
+        res[idx] = pick(&mut view, &observation.known(idx))[idx];
+        updated[idx] = view[idx];
+    }
+    *agents = updated;
+    res
+}
+
+// matches active agents to targets minimising makespan, the matcher is kept
+// between turns and pairs chosen in the previous turn (stored in Agent::targets)
+// seed it after catches renumber agents and targets
+// returns index of the matched target for every agent
+fn assign_targets(map: &Map, agents: &mut [Agent], targets: &[Target],
+                  bottleneck: &mut Bottleneck<impl Matcher>) -> Vec<Option<usize>> {
+    let mut res = vec![None; agents.len()];
+
+    let agents_points = agents.iter()
+        .filter(|x| x.active)
+        .map(|x| x.position)
+        .collect::<Vec<_>>();
+
+    let idxs = agents.iter()
+        .enumerate()
+        .filter(|x| x.1.active)
+        .map(|x| x.0)
+        .collect::<Vec<_>>();
+
+    let targets_points = targets.iter()
+        .map(|x| x.position)
+        .collect::<Vec<_>>();
+
+    let n = agents_points.len();
+    let mut hint = vec![-1; n+targets.len()];
+    for i in 0..n {
+        let prev = agents[idxs[i]].targets;
+        if let Some(t) = targets.iter().position(|x| x.idx as i32 == prev) {
+            hint[i] = (t+n) as i32;
+            hint[t+n] = i as i32;
+        }
+    }
+
+    let edges = makespan_edges(map, &agents_points, &targets_points);
+    let _dd = bottleneck.solve(n, targets.len(), &edges, &hint);
+
+    let matching = bottleneck.matcher.get_matching();
+    for i in 0..n {
+        if matching[i] == -1 { continue; }
+        let t = (matching[i] as usize)-n;
+        res[idxs[i]] = Some(t);
+        agents[idxs[i]].targets = targets[t].idx as i32;
+    }
+
+    res
+}
+
+pub struct MakeSpanHopcroft {
+    bottleneck: Bottleneck<HopcroftKarp>,
+}
+
+impl MakeSpanHopcroft {
+    pub fn new() -> Self {
+        MakeSpanHopcroft { bottleneck: Bottleneck::new() }
+    }
 }
 
-pub struct MakeSpanHopcroft;
 impl AgentStrategy for MakeSpanHopcroft {
     fn pick(&mut self, map: &Map, agents: &mut Vec<Agent>, targets: &Vec<Target>) -> Vec<Direction> {
         let mut res = vec![Direction::None; agents.len()];
 
-        let agents_points = agents.into_iter()
-            .filter(|x| x.active)
-            .map(|x| x.position)
-            .collect::<Vec<_>>();
+        let assigned = assign_targets(map, agents, targets, &mut self.bottleneck);
+        for (idx, t) in assigned.iter().enumerate() {
+            if let Some(t) = t {
+                res[idx] = map.get_direction(&agents[idx].position, &targets[*t].position);
+            }
+        }
 
-        let idxs = agents.into_iter()
-            .enumerate()
-            .filter(|x| x.1.active)
-            .map(|x| x.0)
-            .collect::<Vec<_>>();
+        res
+    }
+
+    // the matcher only carries the previous matching, every agent can be matched on its own
+    fn observes_alone(&self) -> bool { true }
+
+    fn pick_observed(&mut self, map: &Map, agents: &mut Vec<Agent>, observation: &Observation) -> Vec<Direction> {
+        if observation.shared { return self.pick(map, agents, &observation.team()); }
+        pick_each(agents, observation, |view, known| self.pick(map, view, known))
+    }
+}
+
+// Priority Inheritance with Backtracking (Okumura et al.)
+// targets are rematched every turn (same as MakeSpanHopcroft), then agents
+// reserve cells for the next step in priority order, pushing lower priority
+// agents out of the way -> no vertex collisions and no swaps
+pub struct Pibt {
+    priorities: Vec<f64>,
+    bottleneck: Bottleneck<HopcroftKarp>,
+}
 
-        let targets_points = targets.into_iter()
-            .map(|x| x.position)
+impl Pibt {
+    pub fn new() -> Self {
+        Pibt { priorities: Vec::new(), bottleneck: Bottleneck::new() }
+    }
+
+    fn candidates(map: &Map, pos: Point, goal: Point) -> Vec<Point> {
+        let dirs = [Direction::North, Direction::East,
+                    Direction::South, Direction::West, Direction::None];
+        let mut res = dirs.iter()
+            .filter(|d| map.valid_direction(pos, **d))
+            .map(|d| go_direction(pos, *d))
+            .filter(|p| map.valid_point(p))
             .collect::<Vec<_>>();
+        // stable sort -> ties are broken by the order of dirs
+        res.sort_by_key(|p| map.dist_point(p, &goal));
+        res
+    }
 
-        let mut matcher = HopcroftKarp::new();
-        let _dd = makespan_solve(map, &agents_points, &targets_points, &mut matcher);
+    #[allow(clippy::too_many_arguments)]
+    fn pibt(map: &Map, i: usize, parent: Option<usize>, pos: &[Point], goals: &[Point],
+            occupied_now: &HashMap<Point, usize>, occupied_next: &mut HashMap<Point, usize>,
+            next: &mut Vec<Option<Point>>) -> bool {
+        for c in Self::candidates(map, pos[i], goals[i]) {
+            if occupied_next.contains_key(&c) { continue; }
+            // never swap with the agent that pushed us
+            if parent.is_some_and(|p| pos[p] == c) { continue; }
 
-        let matching = matcher.get_matching();
-        for i in 0..agents_points.len() {
-            if matching[i] == -1 { continue; }
-            let t = (matching[i] as usize)-agents_points.len();
-            res[idxs[i]] = map.get_direction(&agents[idxs[i]].position, &targets[t].position);
-            agents[idxs[i]].targets = targets[t].idx as i32;
+            occupied_next.insert(c, i);
+            next[i] = Some(c);
+            if let Some(&j) = occupied_now.get(&c) {
+                if j != i && next[j].is_none()
+                    && !Self::pibt(map, j, Some(i), pos, goals, occupied_now, occupied_next, next) {
+                    continue;
+                }
+            }
+            return true;
         }
+        occupied_next.insert(pos[i], i);
+        next[i] = Some(pos[i]);
+        false
+    }
+}
 
-        res
+impl AgentStrategy for Pibt {
+    fn pick(&mut self, map: &Map, agents: &mut Vec<Agent>, targets: &Vec<Target>) -> Vec<Direction> {
+        let n = agents.len();
+        if self.priorities.len() != n {
+            // fractional part is a fixed tie breaker
+            self.priorities = (0..n).map(|i| i as f64 / n as f64).collect();
+        }
+
+        let pos = agents.iter().map(|x| x.position).collect::<Vec<_>>();
+        let mut goals = pos.clone();
+
+        let assigned = assign_targets(map, agents, targets, &mut self.bottleneck);
+        for (idx, t) in assigned.iter().enumerate() {
+            if let Some(t) = t {
+                goals[idx] = targets[*t].position;
+            }
+        }
+
+        // agents that are not at their goal gain priority, the rest fall back
+        // to the tie breaker (inactive agents always stay at the bottom)
+        for i in 0..n {
+            if agents[i].active && pos[i] != goals[i] { self.priorities[i] += 1.0; }
+            else { self.priorities[i] = self.priorities[i].fract(); }
+        }
+
+        let mut order = (0..n).collect::<Vec<_>>();
+        order.sort_by(|a, b| self.priorities[*b].total_cmp(&self.priorities[*a]));
+
+        let occupied_now = pos.iter()
+            .enumerate()
+            .map(|(i, p)| (*p, i))
+            .collect::<HashMap<_, _>>();
+        let mut occupied_next = HashMap::new();
+        let mut next = vec![None; n];
+
+        for i in order {
+            if next[i].is_some() { continue; }
+            Self::pibt(map, i, None, &pos, &goals, &occupied_now, &mut occupied_next, &mut next);
+        }
+
+        (0..n).map(|i| map.neighbor(&pos[i], &next[i].unwrap())).collect()
     }
 }
 
@@ -146,44 +333,25 @@ impl CollisionFree {
     pub fn prep(&mut self, map: &Map, agents: &mut Vec<Agent>, targets: &Vec<Target>, matcher: &mut impl Matcher) {
         assert!(agents.len() == targets.len());
 
-        // find permuatation
-        let mut left: i32 = 0;
-        let mut right: i32 = 1_000_000_000;
+        // interception times are computed once, then the permutation with the
+        // smallest latest capture time is found directly
         let n = agents.len();
-        let m = targets.len();
-        let mut perm = vec![0; n];
-
-        while left <= right {
-            let mid = left+(right-left)/2;
-            // println!("trying... {}", mid);
-            let mut graph: Vec<Vec<usize>> = vec![Vec::new(); n+m];
-            for (i, agent) in agents.iter().enumerate() {
-                for (j, target) in targets.iter().enumerate() {
-                    let mut single_strat = NoCollisionSingle::new();
-                    single_strat.prep(map, agent, target);
-                    if single_strat.expected_time == -1 { continue; }
-                    if single_strat.expected_time <= mid {
-                        graph[i].push(j+n);
-                        graph[j+n].push(i);
-                    }
-                }
-            }
-            let setu = (0..agents.len()).collect();
-            let setv = (agents.len()..agents.len()+targets.len()).collect();
-            matcher.init(graph, setu, setv);
-            let got = matcher.solve();
-            // println!("got: {}", got);
-            if got == n { // assuming n == m
-                right = mid-1;
-                for idx in 0..n {
-                    perm[idx] = (matcher.get_matching()[idx]-(n as i32)) as usize;
-                }
-            }
-            else {
-                left = mid+1;
+        let mut edges = Vec::new();
+        for (i, agent) in agents.iter().enumerate() {
+            for (j, target) in targets.iter().enumerate() {
+                let mut single_strat = NoCollisionSingle::new();
+                single_strat.prep(map, agent, target);
+                if single_strat.expected_time == -1 { continue; }
+                edges.push((i, j, single_strat.expected_time));
             }
         }
 
+        let bottleneck = bottleneck_solve(n, n, edges, matcher, &[]);
+        assert!(bottleneck != -1, "no assignment of agents to targets");
+        let perm = matcher.get_matching()[0..n].iter()
+            .map(|x| (*x as usize)-n)
+            .collect::<Vec<_>>();
+
         // println!("permutation: {:?}", perm);
 
         // get the result from CollisionAssigned using found permutation
@@ -192,6 +360,61 @@ impl CollisionFree {
         self.goto = assigned.goto;
         self.ready = true;
     }
+
+    // cell of the target path the agent reaches in time with the fewest moves
+    // (earliest on ties), the agent waits there, waiting is free
+    // returns (time, cell), None if the target can not be caught
+    fn nearest_meeting(map: &Map, agent: &Agent, target: &Target) -> Option<(usize, Point)> {
+        let len = target.path.as_ref().unwrap().len();
+        (0..len)
+            .map(|time| (time, target.at_time(time)))
+            .filter(|(time, p)| {
+                let dist = map.dist_point(&agent.position, p);
+                // the target stays at the end of its path
+                dist != usize::MAX && (dist <= *time || *time+1 == len)
+            })
+            .min_by_key(|(time, p)| (map.dist_point(&agent.position, p), *time))
+    }
+
+    // same as prep, but minimises the sum of capture times (SumOfCosts) or the
+    // sum of moves (TotalTravel) instead of the maximum
+    pub fn prep_sum(&mut self, map: &Map, agents: &mut [Agent], targets: &[Target], objective: Objective) {
+        assert!(agents.len() == targets.len());
+        assert!(objective != Objective::Makespan);
+
+        let mut cost = vec![vec![None; targets.len()]; agents.len()];
+        let mut meet = vec![vec![Point{x: 0, y: 0}; targets.len()]; agents.len()];
+        for (i, agent) in agents.iter().enumerate() {
+            for (j, target) in targets.iter().enumerate() {
+                if objective == Objective::TotalTravel {
+                    let Some((_, p)) = Self::nearest_meeting(map, agent, target) else { continue; };
+                    cost[i][j] = Some(map.dist_point(&agent.position, &p) as i64);
+                    meet[i][j] = p;
+                    continue;
+                }
+                let mut single_strat = NoCollisionSingle::new();
+                single_strat.prep(map, agent, target);
+                if single_strat.expected_time == -1 { continue; }
+                cost[i][j] = Some(single_strat.expected_time as i64);
+                meet[i][j] = single_strat.goto;
+            }
+        }
+
+        let mut hungarian = Hungarian::new();
+        hungarian.init(cost);
+        let got = hungarian.solve();
+        assert!(got == agents.len());
+        let perm = hungarian.get_assignment().iter()
+            .map(|x| *x as usize)
+            .collect::<Vec<_>>();
+
+        self.goto = vec![Point{x: 0, y: 0}; agents.len()];
+        for (idx, agent) in agents.iter_mut().enumerate() {
+            self.goto[idx] = meet[idx][perm[idx]];
+            agent.targets = perm[idx] as i32;
+        }
+        self.ready = true;
+    }
 }
 
 impl AgentStrategy for CollisionFree {
@@ -205,204 +428,300 @@ impl AgentStrategy for CollisionFree {
     }
 }
 
+// why NoCollisionFree could not catch every target
+#[derive(Debug, Clone, PartialEq)]
+pub struct Infeasible {
+    pub horizon: usize,
+    // agents that catch a different target within the horizon
+    pub caught: usize,
+    // targets no agent can reach within the horizon
+    pub unreachable: Vec<usize>,
+    // cells and moves (both directions) saturated on the minimum cut
+    pub bottlenecks: Vec<Point>,
+    pub corridors: Vec<(Point, Point)>,
+}
+
+impl fmt::Display for Infeasible {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "only {} agents catch a target within {} turns", self.caught, self.horizon)?;
+        if !self.unreachable.is_empty() {
+            write!(f, ", unreachable targets: {:?}", self.unreachable)?;
+        }
+        if !self.bottlenecks.is_empty() {
+            let cells = self.bottlenecks.iter().map(|p| (p.x, p.y)).collect::<Vec<_>>();
+            write!(f, ", bottleneck cells: {:?}", cells)?;
+        }
+        if !self.corridors.is_empty() {
+            let moves = self.corridors.iter().map(|(a, b)| ((a.x, a.y), (b.x, b.y))).collect::<Vec<_>>();
+            write!(f, ", saturated corridors: {:?}", moves)?;
+        }
+        Ok(())
+    }
+}
+
+// why NoCollisionFree could not make a plan
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlanError {
+    // every agent has to catch a different target
+    SurplusAgents { agents: usize, targets: usize },
+    Infeasible(Infeasible),
+}
+
+impl fmt::Display for PlanError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PlanError::SurplusAgents { agents, targets } => write!(f, "{} agents but only {} targets", agents, targets),
+            PlanError::Infeasible(infeasible) => infeasible.fmt(f),
+        }
+    }
+}
+
+impl From<Infeasible> for PlanError {
+    fn from(infeasible: Infeasible) -> Self {
+        PlanError::Infeasible(infeasible)
+    }
+}
+
 pub struct NoCollisionFree {
-    height: i32,
-    width: i32,
+    // no limit -> layers are added until one after the last target stops adds no flow
+    pub max_horizon: Option<usize>,
+    graph: TimeExpanded,
     ready: bool,
     paths_idx: Vec<usize>,
     paths: Vec<Vec<Direction>>,
 }
 
 impl NoCollisionFree {
-    // grid and two filters
-    const LAYERS: i32 = 3;
-    // number of edges that we map on egdes filter
-    const DIRECTIONS: i32 = 3;
-
     pub fn new() -> Self {
         NoCollisionFree {
-            height: -1,
-            width: -1,
+            max_horizon: None,
+            graph: TimeExpanded::new(),
             ready: false,
             paths_idx: Vec::new(),
             paths: Vec::new(),
         }
     }
 
-    fn conv_expl(&self, layer: i32, x: i32, y: i32) -> i32 {
-        return layer*self.width*self.height*Self::DIRECTIONS+(y*self.width+x);
+    fn construct(&mut self, flow: &mut impl MaxFlow, mid: usize,
+                 map: &Map, agents: &[Agent], targets: &[Target]) {
+        flow.reset();
+        self.construct_with(&mut |from, to, capacity, _cost| flow.add_edge(from, to, capacity),
+                            mid, map, agents, targets, Objective::Makespan);
+        flow.set_source(self.graph.id(Node::Source));
+        flow.set_sink(self.graph.id(Node::Sink));
     }
 
-    fn conv(&self, layer: i32, pnt: &Point) -> i32 {
-        return self.conv_expl(layer, pnt.x as i32, pnt.y as i32);
+    fn construct_costs(&mut self, flow: &mut impl MinCostFlow, mid: usize,
+                       map: &Map, agents: &[Agent], targets: &[Target], objective: Objective) {
+        flow.reset();
+        self.construct_with(&mut |from, to, capacity, cost| flow.add_cost_edge(from, to, capacity, cost),
+                            mid, map, agents, targets, objective);
+        flow.set_source(self.graph.id(Node::Source));
+        flow.set_sink(self.graph.id(Node::Sink));
     }
 
-    fn conv_edge_expl(&self, layer: i32, x: i32, y: i32, dir: &Direction) -> i32 {
-        let cell = self.conv_expl(layer, x*3, y*3);
-        // no move -> special 3rd, or just skip filter
-        // e, s -> normal
-        // w, n -> move back and create edge
-        // num edges -> 3n
-        if *dir == Direction::None { return cell; }
-        else if *dir == Direction::East || *dir == Direction::South {
-            let mut mv = 1;
-            if *dir == Direction::South { mv = 2; }
-            // println!("conv_edge: {} {} {:?} -> {} {}", x, y, dir, cell, mv);
-            return cell + mv;
-        }
-        else {
-            let mut tmp = Point { x: x as usize, y: y as usize };
-            tmp = go_direction(tmp, *dir);
-            // println!("conv_edge: {} {} {:?} -> {} {} {:?}", x, y, dir, tmp.x, tmp.y, Map::reverse_direction(dir));
-            return self.conv_edge(layer, &tmp, &Map::reverse_direction(dir));
+    // builds the network edge by edge with add_edge(from, to, capacity, cost),
+    // costs follow the objective: capture time for SumOfCosts, moves for TotalTravel
+    fn construct_with(&mut self, add_edge: &mut impl FnMut(i32, i32, i32, i64), mid: usize,
+                      map: &Map, agents: &[Agent], targets: &[Target], objective: Objective) {
+        self.construct_base(add_edge, agents, targets);
+        for timer in 0..mid {
+            self.construct_layer(add_edge, timer, map, targets, objective);
         }
     }
 
-    fn conv_edge(&self, layer: i32, pnt: &Point, dir: &Direction) -> i32 {
-        return self.conv_edge_expl(layer, pnt.x as i32, pnt.y as i32, dir);
-    }
-
-    fn reconv_point(&self, time: i32, pos: i32) -> Point {
-        let mut notime = pos;
-        if time > 0 { notime = pos%(time*self.width*self.height*Self::DIRECTIONS); }
-        let x = (notime%self.width) as usize;
-        let y = (notime/self.width) as usize;
-        Point {x, y}
-    }
-
-    fn construct(&self, flow: &mut impl MaxFlow, sink: i32, source: i32, mid: i32,
-                 map: &Map, agents: &Vec<Agent>, targets: &Vec<Target>) {
-
-        let directions = vec![Direction::North, Direction::East,
-                              Direction::South, Direction::West,
-                              Direction::None];
-        flow.reset();
-        flow.set_source(source);
-        flow.set_sink(sink);
-        // println!("source={}, sink={}", source, sink);
-        // println!("connecting source to agents:");
+    // source to agents, collectors to sink and captures at time 0
+    fn construct_base(&mut self, add_edge: &mut impl FnMut(i32, i32, i32, i64),
+                      agents: &[Agent], targets: &[Target]) {
+        self.graph.reset();
+        let source = self.graph.id(Node::Source);
+        let sink = self.graph.id(Node::Sink);
         for agent in agents.iter() {
-            flow.add_edge(source, self.conv(0, &agent.position), 1);
+            add_edge(source, self.graph.id(Node::Cell { time: 0, point: agent.position }), 1, 0);
         }
-        // println!("======");
 
-        for timer in 0..mid {
-            for x in 0..self.width {
-                for y in 0..self.height {
-                    let pnt = Point{x: x as usize, y: y as usize};
-                    for dir in directions.iter() {
-                        if !map.valid_point(&pnt) || !map.valid_direction(pnt, *dir) { continue; }
-                        let nxt = go_direction(pnt, *dir);
-                        if !map.valid_point(&nxt) { continue; }
-                        // println!("trying: pnt={:?} {:?} -> nxt={:?}", pnt, *dir, nxt);
-                        let conv_edge = self.conv_edge(Self::LAYERS*timer+1, &pnt, dir);
-                        flow.add_edge(self.conv(Self::LAYERS*timer, &pnt), conv_edge, 1);
-                        flow.add_edge(conv_edge, self.conv(Self::LAYERS*timer+2, &nxt), 1); // zle double edges
-                    }
-                    // cell filter
-                    // println!("cell filter: timer={} x={} y={}", timer, x, y);
-                    flow.add_edge(self.conv(Self::LAYERS*timer+2, &pnt), self.conv(Self::LAYERS*timer+3, &pnt), 1);
-                }
-            }
+        for (idx, target) in targets.iter().enumerate() {
+            let collector = self.graph.id(Node::Collector(idx));
+            add_edge(collector, sink, 1, 0);
+            add_edge(self.graph.id(Node::Cell { time: 0, point: target.at_time(0) }), collector, 1, 0);
         }
+    }
 
-        let mut collector = sink-1; // assuming sink is the smallest node (in terms of id)
-        for target in targets.iter() {
-            // println!("{:?} {} {:?}", &target.at_time(mid as usize),
-            //         self.conv(2*mid, &target.at_time(mid as usize)),
-            //         self.reconv_point(2*mid, self.conv(2*mid, &target.at_time(mid as usize))));
-            flow.add_edge(collector, sink, 1);
-            for time in 0..mid+1 {
-                flow.add_edge(self.conv(Self::LAYERS*time, &target.at_time(time as usize)), collector, 1);
+    // moves from time timer to timer+1 and captures at time timer+1
+    fn construct_layer(&mut self, add_edge: &mut impl FnMut(i32, i32, i32, i64), timer: usize,
+                       map: &Map, targets: &[Target], objective: Objective) {
+        let directions = [Direction::North, Direction::East,
+                          Direction::South, Direction::West,
+                          Direction::None];
+        for x in 0..map.width {
+            for y in 0..map.height {
+                let pnt = Point{x, y};
+                if !map.valid_point(&pnt) { continue; }
+                for dir in directions.iter() {
+                    if !map.valid_direction(pnt, *dir) { continue; }
+                    let nxt = go_direction(pnt, *dir);
+                    if !map.valid_point(&nxt) { continue; }
+                    let cost = if objective == Objective::TotalTravel && *dir != Direction::None { 1 } else { 0 };
+                    self.graph.edge_gadget(add_edge, timer, pnt, nxt, cost);
+                }
+                self.graph.vertex_gadget(add_edge, timer+1, pnt, 1);
             }
-            collector -= 1;
         }
 
+        let time = timer+1;
+        let cost = if objective == Objective::SumOfCosts { time as i64 } else { 0 };
+        for (idx, target) in targets.iter().enumerate() {
+            let cell = self.graph.id(Node::Cell { time, point: target.at_time(time) });
+            add_edge(cell, self.graph.id(Node::Collector(idx)), 1, cost);
+        }
     }
 
-    pub fn prep(&mut self, map: &Map, agents: &mut Vec<Agent>, targets: &Vec<Target>, flow: &mut impl MaxFlow) {
-        self.height = map.height as i32;
-        self.width = map.width as i32;
-
-        let source = -1;
-        let sink = -2;
+    // time expanded network used by prep for a fixed horizon
+    pub fn network(&mut self, flow: &mut impl MaxFlow, horizon: usize, map: &Map,
+                   agents: &[Agent], targets: &[Target]) {
+        self.construct(flow, horizon, map, agents, targets);
+    }
 
-        let mut left: i32 = 0;
-        let mut right: i32 = 150;
-        let mut res: i32 = -1;
+    // smallest horizon in which every agent catches a different target
+    // the network grows one layer at a time and the flow found so far is kept,
+    // on return flow holds the max flow for the returned horizon
+    fn horizon(&mut self, map: &Map, agents: &[Agent], targets: &[Target],
+               flow: &mut impl MaxFlow) -> Result<usize, PlanError> {
+        if agents.len() > targets.len() {
+            return Err(PlanError::SurplusAgents { agents: agents.len(), targets: targets.len() });
+        }
 
-        while left <= right {
-            let mid = left+(right-left)/2;
-            // println!("mid={}", mid);
-            // println!("agents={:?}", agents);
-            // println!("targets={:?}", targets);
+        // ignoring collisions gives a quick answer for hopeless instances
+        // and a lower bound on the horizon (bottleneck of earliest capture times)
+        let bound = self.max_horizon.unwrap_or(usize::MAX);
+        let mut edges = Vec::new();
+        for (i, agent) in agents.iter().enumerate() {
+            for (j, target) in targets.iter().enumerate() {
+                if let Some(time) = Self::capture_time(map, agent, target, bound) {
+                    edges.push((i, j, time as i32));
+                }
+            }
+        }
+        let unreachable = (0..targets.len())
+            .filter(|j| edges.iter().all(|e| e.1 != *j))
+            .collect::<Vec<_>>();
+        let latest = edges.iter().map(|e| e.2 as usize).max().unwrap_or(0);
+        let lower = bottleneck_solve(agents.len(), targets.len(), edges, &mut HopcroftKarp::new(), &[]);
+        if lower == -1 {
+            // no assignment even without collisions, captures after latest add nothing,
+            // so the cut of that network explains it
+            let horizon = self.max_horizon.unwrap_or(latest);
+            self.construct(flow, horizon, map, agents, targets);
+            flow.solve();
+            return Err(self.explain(flow, horizon, unreachable).into());
+        }
+        // layers after every target stopped are all the same, one adding no flow means none will
+        let stopped = targets.iter().map(|x| x.path.as_ref().unwrap().len()-1).max().unwrap_or(0);
 
-            self.construct(flow, sink, source, mid, map, agents, targets);
-            // println!("finished construction");
+        let mut res = lower as usize;
+        self.construct(flow, res, map, agents, targets);
+        flow.solve();
 
-            if flow.get_flow() == agents.len() as i32 {
-                right = mid-1;
-                res = mid;
+        while flow.get_flow() < agents.len() as i32 {
+            if self.max_horizon == Some(res) {
+                return Err(self.explain(flow, res, unreachable).into());
             }
-            else {
-                left = mid+1;
+            let before = flow.get_flow();
+            self.construct_layer(&mut |from, to, capacity, _cost| flow.add_edge(from, to, capacity),
+                                 res, map, targets, Objective::Makespan);
+            flow.solve();
+            res += 1;
+            if res > stopped && flow.get_flow() == before {
+                return Err(self.explain(flow, res, unreachable).into());
             }
+            // println!("res={} flow={}", res, flow.get_flow());
+        }
+        Ok(res)
+    }
 
-            // println!("finished: {}, {}, l:{}, r:{}", mid, res, left, right);
-            // println!("flow={}", flow.get_flow());
-            // panic!();
+    // earliest time (up to horizon) agent can catch target, ignoring other agents
+    fn capture_time(map: &Map, agent: &Agent, target: &Target, horizon: usize) -> Option<usize> {
+        let end = target.path.as_ref().unwrap().len()-1;
+        for time in 0..std::cmp::min(end, horizon.saturating_add(1)) {
+            if map.dist_point(&agent.position, &target.at_time(time)) <= time { return Some(time); }
         }
+        // target stands still from end on
+        let dist = map.dist_point(&agent.position, &target.at_time(end));
+        if dist == usize::MAX { return None; }
+        let time = std::cmp::max(end, dist);
+        if time <= horizon { Some(time) } else { None }
+    }
 
-        // println!("res: {}", res);
-        if res == -1 {
-            println!("couldnt find any path :(");
-            panic!();
+    // reads saturated cells and moves off the minimum cut of the current network
+    fn explain(&self, flow: &mut impl MaxFlow, horizon: usize, unreachable: Vec<usize>) -> Infeasible {
+        let mut bottlenecks = Vec::new();
+        let mut corridors = Vec::new();
+        for (from, to) in flow.min_cut() {
+            match (self.graph.node(from), self.graph.node(to)) {
+                (Some(Node::Arrival { point, .. }), Some(Node::Cell { .. })) => bottlenecks.push(point),
+                (_, Some(Node::Edge { a, b, .. })) | (Some(Node::Edge { a, b, .. }), _) => {
+                    if a == b { bottlenecks.push(a); }
+                    else { corridors.push((a, b)); }
+                },
+                _ => {},
+            }
         }
+        bottlenecks.sort_by_key(|p| (p.x, p.y));
+        bottlenecks.dedup();
+        corridors.sort_by_key(|(a, b)| (a.x, a.y, b.x, b.y));
+        corridors.dedup();
 
+        Infeasible {
+            horizon,
+            caught: flow.get_flow() as usize,
+            unreachable,
+            bottlenecks,
+            corridors,
+        }
+    }
+
+    pub fn prep(&mut self, map: &Map, agents: &mut [Agent], targets: &[Target],
+                flow: &mut impl MaxFlow) -> Result<(), PlanError> {
+        self.horizon(map, agents, targets, flow)?;
+        self.extract_paths(map, agents, flow);
+        Ok(())
+    }
+
+    // same as prep, but among plans with the smallest makespan picks the one
+    // minimising objective (sum of capture times or number of moves)
+    pub fn prep_sum(&mut self, map: &Map, agents: &mut [Agent], targets: &[Target],
+                    flow: &mut impl MaxFlow, cost_flow: &mut impl MinCostFlow,
+                    objective: Objective) -> Result<(), PlanError> {
+        let res = self.horizon(map, agents, targets, flow)?;
+        self.construct_costs(cost_flow, res, map, agents, targets, objective);
+        cost_flow.solve();
+        assert!(cost_flow.get_flow() == agents.len() as i32);
+        self.extract_paths(map, agents, cost_flow);
+        Ok(())
+    }
+
+    fn extract_paths(&mut self, map: &Map, agents: &mut [Agent], flow: &impl MaxFlow) {
         self.paths_idx = vec![0; agents.len()];
         self.paths = vec![Vec::new(); agents.len()];
 
-        self.construct(flow, sink, source, res, map, agents, targets);
-        flow.solve();
-
-        let mut last_pos = vec![Point{x:0, y:0}; agents.len()];
-        for (idx, agent) in agents.iter().enumerate() {
-            last_pos[idx] = agent.position;
-        }
-
-        // println!("res: {}", res);
-        for timer in 0..res {
-            for idx in 0..agents.len() {
-                let conv_pos = self.conv(Self::LAYERS*timer, &last_pos[idx]);
-                let mut edge = flow.get_saturated_edge(conv_pos);
-                // println!("\nconv_pos={}", conv_pos);
-                if edge.is_none() {
-                    // println!("dead end: t={} idx={} x={} y={}", timer, idx, last_pos[idx].x, last_pos[idx].y);
-                    panic!("failed to recontruct path");
-                }
-                // println!("added: t={} idx={} x={} y={}", timer, idx, last_pos[idx].x, last_pos[idx].y);
-                let edge_filter = edge.unwrap().1;
-                // println!("edge_filter: {}", edge_filter);
-                let mut edge = flow.get_saturated_edge(edge_filter);
-                let cell_filter = edge.unwrap().1;
-                // println!("cell_filter: {}", cell_filter);
-                let mut edge = flow.get_saturated_edge(cell_filter);
-                let next_cell = edge.unwrap().1;
-                // println!("next_cell: {}", next_cell);
-                let npoint = self.reconv_point(Self::LAYERS*(timer+1), next_cell);
-                self.paths[idx].push(map.neighbor(&last_pos[idx], &npoint));
-                last_pos[idx] = npoint;
-                // println!(" -> x={} y={} dir={:?}\n", last_pos[idx].x, last_pos[idx].y, self.paths[idx].last());
-            }
-        }
+        let mut done = vec![false; agents.len()];
+        for (path, _) in flow.decompose() {
+            let nodes = path.iter().map(|x| self.graph.node(*x).unwrap()).collect::<Vec<_>>();
+            let cells = nodes.iter()
+                .filter_map(|x| match x { Node::Cell { point, .. } => Some(*point), _ => None })
+                .collect::<Vec<_>>();
+            let target = nodes.iter()
+                .find_map(|x| match x { Node::Collector(idx) => Some(*idx), _ => None })
+                .expect("failed to recontruct path");
 
-        for idx in 0..agents.len() {
-            let conv_pos = self.conv(Self::LAYERS*res, &last_pos[idx]);
-            let edge = flow.get_saturated_edge(conv_pos);
-            // println!("next: {}", edge.unwrap().1);
-            let target_id = -(edge.unwrap().1 - (-3));
-            agents[idx].targets = target_id;
+            let idx = (0..agents.len())
+                .find(|idx| !done[*idx] && agents[*idx].position == cells[0])
+                .expect("failed to recontruct path");
+            done[idx] = true;
+            self.paths[idx] = cells.windows(2).map(|x| map.neighbor(&x[0], &x[1])).collect();
+            agents[idx].targets = target as i32;
         }
+        assert!(done.iter().all(|x| *x), "failed to recontruct path");
 
         self.ready = true;
     }
@@ -424,28 +743,792 @@ impl AgentStrategy for NoCollisionFree {
     }
 }
 
+// optimal play against a single target from the exact game solution (small maps only)
+pub struct OptimalAgents {
+    solver: Rc<GameSolver>,
+}
+
+impl OptimalAgents {
+    pub fn new(solver: Rc<GameSolver>) -> Self {
+        OptimalAgents { solver }
+    }
+}
+
+impl AgentStrategy for OptimalAgents {
+    fn pick(&mut self, map: &Map, agents: &mut Vec<Agent>, targets: &Vec<Target>) -> Vec<Direction> {
+        let Some(target) = targets.first() else { return vec![Direction::None; agents.len()]; };
+        let positions = agents.iter().map(|x| x.position).collect::<Vec<_>>();
+        self.solver.agent_move(map, &positions, target.position, target.timer)
+    }
+}
+
+
+// for targets that move at random with a known model (MarkovTarget::new(MarkovModel::Uniform, d_time)
+// for RandomTarget), replans every turn: beliefs about the targets are propagated for horizon
+// turns and every agent heads for the cell where it meets a target with the highest probability
+// (summed from the turn it can get there on), agents take different targets while they can
+pub struct ExpectedCapture {
+    model: Box<dyn TransitionModel>,
+    pub horizon: usize,
+    // positions of targets in the previous turn, give their headings
+    previous: HashMap<usize, Point>,
+}
+
+impl ExpectedCapture {
+    pub fn new(model: Box<dyn TransitionModel>, horizon: usize) -> Self {
+        ExpectedCapture { model, horizon, previous: HashMap::new() }
+    }
+
+    // position distributions of target after 0..horizon more turns
+    fn forecast(&self, map: &Map, target: &Target) -> Vec<HashMap<Point, f64>> {
+        let heading = match self.previous.get(&target.idx) {
+            Some(p) if p.x.abs_diff(target.position.x) + p.y.abs_diff(target.position.y) <= 1 =>
+                map.neighbor(p, &target.position),
+            _ => Direction::None,
+        };
+        let state = MotionState { position: target.position, timer: target.timer, heading };
+        self.model.distribution(map, state, self.horizon)
+    }
+
+    // (probability, -turns, cell) of the best meeting cell, the agent moves now and the target
+    // after it, so the agent makes the cell at forecast[time] in time+1 moves and then waits there;
+    // a cell scores the probability mass over its whole arrival window (at most 1, the expected
+    // turns the target spends there bound the chance to meet it), ties go to earlier meetings
+    fn best_cell(map: &Map, agent: Point, forecast: &[HashMap<Point, f64>]) -> (f64, i64, Point) {
+        let mut window: HashMap<Point, (f64, usize)> = HashMap::new();
+        for (time, dist) in forecast.iter().enumerate() {
+            for (cell, pr) in dist.iter() {
+                if *pr == 0.0 || map.dist_point(&agent, cell) > time+1 { continue; }
+                window.entry(*cell).or_insert((0.0, time)).0 += pr;
+            }
+        }
+        let mut best = (0.0, i64::MIN, agent);
+        for (cell, (pr, time)) in window.iter() {
+            let now = (pr.min(1.0), -(*time as i64), *cell);
+            if now.0 > best.0 || now.0 == best.0 && now.1 > best.1 {
+                best = now;
+            }
+        }
+        best
+    }
+
+    // directions for agents toward their meeting cells
+    fn plan(&self, map: &Map, agents: &[Agent], targets: &[Target]) -> Vec<Direction> {
+        let forecasts = targets.iter().map(|x| self.forecast(map, x)).collect::<Vec<_>>();
+        let mut options = Vec::new();
+        let catches = |agent: &Agent, target: &Target| agent.targets == -1 || agent.targets == target.idx as i32;
+        for (i, agent) in agents.iter().enumerate() {
+            if !agent.active { continue; }
+            for (t, forecast) in forecasts.iter().enumerate() {
+                if !catches(agent, &targets[t]) { continue; }
+                let best = ExpectedCapture::best_cell(map, agent.position, forecast);
+                if best.0 > 0.0 { options.push((best, i, t)); }
+            }
+        }
+        // most likely meetings first, one agent per target while there are free targets
+        options.sort_by(|a, b| (b.0.0, b.0.1).partial_cmp(&(a.0.0, a.0.1)).unwrap());
+        let mut goal = vec![None; agents.len()];
+        let mut taken = vec![false; targets.len()];
+        for everyone in [false, true] {
+            for ((_, _, cell), i, t) in options.iter() {
+                if goal[*i].is_some() || taken[*t] && !everyone { continue; }
+                goal[*i] = Some(*cell);
+                taken[*t] = true;
+            }
+        }
+        // no target in reach within the horizon, closest one it can catch
+        for (i, agent) in agents.iter().enumerate() {
+            if !agent.active || goal[i].is_some() { continue; }
+            goal[i] = targets.iter()
+                .filter(|target| catches(agent, target))
+                .min_by_key(|target| map.dist_point(&agent.position, &target.position))
+                .map(|target| target.position);
+        }
+
+        agents.iter()
+            .zip(goal.iter())
+            .map(|(agent, goal)| match goal {
+                Some(cell) => map.get_direction(&agent.position, cell),
+                None => Direction::None,
+            })
+            .collect()
+    }
+}
+
+impl AgentStrategy for ExpectedCapture {
+    fn pick(&mut self, map: &Map, agents: &mut Vec<Agent>, targets: &Vec<Target>) -> Vec<Direction> {
+        let res = self.plan(map, agents, targets);
+        self.previous = targets.iter().map(|x| (x.idx, x.position)).collect();
+        res
+    }
+
+    fn observes_alone(&self) -> bool { true }
+
+    // every agent forecasts from the headings of the previous turn, then the team remembers
+    // where it saw the targets
+    fn pick_observed(&mut self, map: &Map, agents: &mut Vec<Agent>, observation: &Observation) -> Vec<Direction> {
+        if observation.shared { return self.pick(map, agents, &observation.team()); }
+        let res = pick_each(agents, observation, |view, known| self.plan(map, view, known));
+        self.previous = observation.team().iter().map(|x| (x.idx, x.position)).collect();
+        res
+    }
+}
+
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::runner::Runner;
+    use crate::target_strategies::*;
+    use crate::bench::gen_set;
+    use crate::flow::{Dinic, FordFulkerson, PushRelabel, SuccessiveShortestPaths};
+    use crate::observation::{ObservationModel, Sensing};
+    use std::collections::HashSet;
+    use rand::Rng;
+
+    fn run_pibt(map: &Map, agents: &mut Vec<Agent>, targets: &mut Vec<Target>,
+                target_strat: &mut dyn TargetStrategy, max_iter: usize) -> usize {
+        let mut strat = Pibt::new();
+        play(map, agents, targets, target_strat, |agents, targets| strat.pick(map, agents, targets), max_iter)
+    }
+
+    // plays a game turn by turn (same order as Runner) and checks every agent move,
+    // caught agents leave the game and block nobody
+    fn play(map: &Map, agents: &mut Vec<Agent>, targets: &mut Vec<Target>, target_strat: &mut dyn TargetStrategy,
+            mut pick: impl FnMut(&mut Vec<Agent>, &Vec<Target>) -> Vec<Direction>, max_iter: usize) -> usize {
+        let d_time = targets[0].timer;
+        let mut turns = 0;
+        while !targets.is_empty() && turns < max_iter {
+            let target_dirs = target_strat.pick(map, agents, targets);
+            for (idx, dir) in target_dirs.iter().enumerate() {
+                targets[idx].position = go_direction(targets[idx].position, *dir);
+                if *dir == Direction::None { targets[idx].timer = d_time; }
+                else { targets[idx].timer -= 1; }
+            }
+
+            let before = agents.iter().map(|x| x.position).collect::<Vec<_>>();
+            let dirs = pick(agents, targets);
+            for (idx, dir) in dirs.iter().enumerate() {
+                assert!(map.valid_direction(agents[idx].position, *dir));
+                agents[idx].position = go_direction(agents[idx].position, *dir);
+                assert!(map.valid_point(&agents[idx].position));
+            }
+
+            let after = agents.iter().map(|x| x.position).collect::<Vec<_>>();
+            let playing = (0..after.len()).filter(|i| agents[*i].active).collect::<Vec<_>>();
+            assert_eq!(playing.iter().map(|i| after[*i]).collect::<HashSet<_>>().len(), playing.len(),
+                       "vertex collision: {:?}", after);
+            for i in playing.iter() {
+                for j in playing.iter() {
+                    assert!(i == j || before[*i] != after[*j] || before[*j] != after[*i], "swap: {} {}", i, j);
+                }
+            }
+
+            for agent in agents.iter_mut() {
+                if !agent.active { continue; }
+                if targets.iter().any(|t| t.position == agent.position && t.idx as i32 == agent.targets) {
+                    agent.active = false;
+                    targets.retain(|t| t.idx as i32 != agent.targets);
+                }
+            }
+            turns += 1;
+        }
+        turns
+    }
+
+    #[test]
+    fn pibt_random_target() {
+        let map = Map::new("resources/maps/box.map");
+        for _ in 0..20 {
+            let mut agents = agents_random(&map, 4);
+            let mut targets = targets_random(&map, 3, 3);
+            let mut strat = RandomTarget {};
+            run_pibt(&map, &mut agents, &mut targets, &mut strat, 1000);
+            assert!(targets.is_empty());
+        }
+    }
+
+    #[test]
+    fn observed_moves_are_valid() {
+        let map = Map::new("resources/maps/box.map");
+        let mut rng = rand::thread_rng();
+        let model = ObservationModel { sensing: Sensing::Radius(4), shared: true };
+        for _ in 0..10 {
+            let Ok((mut all_agents, mut all_targets)) = gen_set(&map, 1, 3, 3, 3, &mut rng, Vec::new(), Vec::new())
+                else { continue; };
+            let agents = all_agents.pop().unwrap();
+            let mut targets = all_targets.pop().unwrap();
+            let mut target_strat = TargetFollowPath::new(3, &map, targets.iter().map(|x| x.position).collect(),
+                                                         &mut targets, true, PathLength::Fixed(30), SAME_DIR);
+
+            // plans only follow their paths, a turn is one joint pick
+            let mut strat = NoCollisionFree::new();
+            let mut planned = agents.clone();
+            strat.prep(&map, &mut planned, &targets, &mut Dinic::new()).unwrap();
+            assert!(!strat.observes_alone());
+            let mut now = targets.clone();
+            target_strat.flush();
+            play(&map, &mut planned, &mut now, &mut target_strat, |agents, targets|
+                 strat.pick_observed(&map, agents, &model.observe(&map, agents, targets)), 200);
+            assert!(now.is_empty());
+
+            let mut strat = Pibt::new();
+            let mut now_agents = agents.clone();
+            assert!(!strat.observes_alone());
+            let mut now = targets.clone();
+            target_strat.flush();
+            play(&map, &mut now_agents, &mut now, &mut target_strat, |agents, targets|
+                 strat.pick_observed(&map, agents, &model.observe(&map, agents, targets)), 200);
+        }
+    }
+
+    #[test]
+    fn pibt_maximize_min_dist() {
+        let map = Map::new("resources/maps/box.map");
+        for _ in 0..20 {
+            let mut agents = agents_random(&map, 3);
+            let mut targets = targets_random(&map, 3, 3);
+            let mut strat = MaximizeMinDist {};
+            run_pibt(&map, &mut agents, &mut targets, &mut strat, 1000);
+            assert!(targets.is_empty());
+        }
+    }
+
+    #[test]
+    fn pibt_corridor() {
+        // agents have to pass each other in a one tile wide corridor
+        let map = Map::new("resources/maps/simple.map");
+        let mut agents = agents_from(&vec![Point{x: 1, y: 3}, Point{x: 1, y: 2}]);
+        let mut targets = targets_from(&vec![Point{x: 9, y: 1}, Point{x: 9, y: 3}], i32::MAX);
+        for (idx, target) in targets.iter_mut().enumerate() { target.idx = idx; }
+        let mut strat = TargetFollowPath::new(2, &map, targets.iter().map(|x| x.position).collect(),
+                                              &mut targets, false, PathLength::Fixed(0), SAME_DIR);
+        run_pibt(&map, &mut agents, &mut targets, &mut strat, 100);
+        assert!(targets.is_empty());
+    }
+
+    fn capture_times(map: &Map, agents: &[Agent], targets: &[Target]) -> Vec<Vec<i32>> {
+        agents.iter().map(|agent| targets.iter().map(|target| {
+            let mut single_strat = NoCollisionSingle::new();
+            single_strat.prep(map, agent, target);
+            single_strat.expected_time
+        }).collect()).collect()
+    }
+
+    fn permutations(n: usize) -> Vec<Vec<usize>> {
+        if n == 0 { return vec![Vec::new()]; }
+        let mut res = Vec::new();
+        for perm in permutations(n-1) {
+            for pos in 0..n {
+                let mut now = perm.clone();
+                now.insert(pos, n-1);
+                res.push(now);
+            }
+        }
+        res
+    }
+
+    #[test]
+    fn collision_free_sum_of_costs() {
+        let map = Map::new("resources/maps/box.map");
+        for _ in 0..20 {
+            let (mut all_agents, mut all_targets) = gen_set(&map, 1, 3, 4, 4, &mut rand::thread_rng(),
+                                                            Vec::new(), Vec::new()).unwrap();
+            let agents = all_agents.pop().unwrap();
+            let mut targets = all_targets.pop().unwrap();
+            let _ = TargetFollowPath::new(4, &map, targets.iter().map(|x| x.position).collect(),
+                                          &mut targets, true, PathLength::Fixed(30), SAME_DIR);
+            let times = capture_times(&map, &agents, &targets);
+
+            let best = permutations(4).iter()
+                .map(|perm| (0..4).map(|i| times[i][perm[i]]).sum::<i32>())
+                .min().unwrap();
+            let best_makespan = permutations(4).iter()
+                .map(|perm| (0..4).map(|i| times[i][perm[i]]).max().unwrap())
+                .min().unwrap();
+
+            let mut sum_agents = agents.clone();
+            CollisionFree::new().prep_sum(&map, &mut sum_agents, &targets, Objective::SumOfCosts);
+            let got = (0..4).map(|i| times[i][sum_agents[i].targets as usize]).collect::<Vec<_>>();
+            assert_eq!(got.iter().sum::<i32>(), best);
+
+            // moves to the closest cell of the target path reached in time, waiting is free
+            let travel = agents.iter().map(|agent| targets.iter().map(|target| {
+                let path = target.path.as_ref().unwrap();
+                path.iter().enumerate()
+                    .map(|(time, p)| (time, map.dist_point(&agent.position, p)))
+                    .filter(|(time, dist)| *dist <= *time || *time+1 == path.len())
+                    .map(|(_, dist)| dist)
+                    .min().unwrap()
+            }).collect::<Vec<_>>()).collect::<Vec<_>>();
+            let best_travel = permutations(4).iter()
+                .map(|perm| (0..4).map(|i| travel[i][perm[i]]).sum::<usize>())
+                .min().unwrap();
+            let mut travel_agents = agents.clone();
+            let mut strat = CollisionFree::new();
+            strat.prep_sum(&map, &mut travel_agents, &targets, Objective::TotalTravel);
+            let moves = (0..4).map(|i| map.dist_point(&agents[i].position, &strat.goto[i])).sum::<usize>();
+            assert_eq!(moves, best_travel);
+            assert_eq!(moves, (0..4).map(|i| travel[i][travel_agents[i].targets as usize]).sum::<usize>());
+            let sum_moves = (0..4).map(|i| travel[i][sum_agents[i].targets as usize]).sum::<usize>();
+            assert!(moves <= sum_moves);
+
+            let mut makespan_agents = agents.clone();
+            CollisionFree::new().prep(&map, &mut makespan_agents, &targets, &mut HopcroftKarp::new());
+            let makespan = (0..4).map(|i| times[i][makespan_agents[i].targets as usize]).max().unwrap();
+            assert_eq!(makespan, best_makespan);
+            assert!(makespan <= *got.iter().max().unwrap());
+        }
+    }
+
+    #[test]
+    fn no_collision_free_backends() {
+        let map = Map::new("resources/maps/tunnel.map");
+        for _ in 0..5 {
+            let (mut all_agents, mut all_targets) = gen_set(&map, 1, 5, 2, 2, &mut rand::thread_rng(),
+                                                            Vec::new(), Vec::new()).unwrap();
+            let agents = all_agents.pop().unwrap();
+            let mut targets = all_targets.pop().unwrap();
+            let _ = TargetFollowPath::new(2, &map, targets.iter().map(|x| x.position).collect(),
+                                          &mut targets, true, PathLength::Fixed(20), SAME_DIR);
+
+            let mut ff = NoCollisionFree::new();
+            ff.prep(&map, &mut agents.clone(), &targets, &mut FordFulkerson::new()).unwrap();
+            let mut dinic = NoCollisionFree::new();
+            dinic.prep(&map, &mut agents.clone(), &targets, &mut Dinic::new()).unwrap();
+            let mut pr = NoCollisionFree::new();
+            pr.prep(&map, &mut agents.clone(), &targets, &mut PushRelabel::new()).unwrap();
+            let horizon = |strat: &NoCollisionFree| strat.paths.iter().map(|x| x.len()).max().unwrap();
+            assert_eq!(horizon(&ff), horizon(&dinic));
+            assert_eq!(horizon(&ff), horizon(&pr));
+        }
+    }
+
+    #[test]
+    fn no_collision_free_incremental_horizon() {
+        // growing the network layer by layer has to agree with building it from scratch
+        let map = Map::new("resources/maps/tunnel.map");
+        for _ in 0..5 {
+            let (mut all_agents, mut all_targets) = gen_set(&map, 1, 5, 3, 3, &mut rand::thread_rng(),
+                                                            Vec::new(), Vec::new()).unwrap();
+            let agents = all_agents.pop().unwrap();
+            let mut targets = all_targets.pop().unwrap();
+            let _ = TargetFollowPath::new(3, &map, targets.iter().map(|x| x.position).collect(),
+                                          &mut targets, true, PathLength::Fixed(20), SAME_DIR);
+
+            let mut strat = NoCollisionFree::new();
+            let mut flow = Dinic::new();
+            let res = strat.horizon(&map, &agents, &targets, &mut flow).unwrap();
+            assert_eq!(flow.get_flow(), agents.len() as i32);
+            for horizon in 0..=res {
+                let mut scratch = Dinic::new();
+                strat.network(&mut scratch, horizon, &map, &agents, &targets);
+                assert_eq!(scratch.get_flow() == agents.len() as i32, horizon == res);
+            }
+        }
+    }
+
+    #[test]
+    fn no_collision_free_sum_of_costs() {
+        let map = Map::new("resources/maps/tunnel.map");
+        let moves = |strat: &NoCollisionFree| strat.paths.iter().flatten().filter(|x| **x != Direction::None).count();
+        let capture_sum = |strat: &NoCollisionFree| strat.paths.iter().map(|x| x.len()).sum::<usize>();
+        let horizon = |strat: &NoCollisionFree| strat.paths.iter().map(|x| x.len()).max().unwrap();
+        for _ in 0..5 {
+            let (mut all_agents, mut all_targets) = gen_set(&map, 1, 5, 3, 3, &mut rand::thread_rng(),
+                                                            Vec::new(), Vec::new()).unwrap();
+            let agents = all_agents.pop().unwrap();
+            let mut targets = all_targets.pop().unwrap();
+            let _ = TargetFollowPath::new(3, &map, targets.iter().map(|x| x.position).collect(),
+                                          &mut targets, true, PathLength::Fixed(20), SAME_DIR);
+
+            let mut makespan = NoCollisionFree::new();
+            makespan.prep(&map, &mut agents.clone(), &targets, &mut Dinic::new()).unwrap();
+
+            let mut sum = NoCollisionFree::new();
+            let mut cost_flow = SuccessiveShortestPaths::new();
+            sum.prep_sum(&map, &mut agents.clone(), &targets, &mut Dinic::new(), &mut cost_flow, Objective::SumOfCosts).unwrap();
+            assert_eq!(horizon(&sum), horizon(&makespan));
+            assert_eq!(cost_flow.get_cost(), capture_sum(&sum) as i64);
+            assert!(capture_sum(&sum) <= capture_sum(&makespan));
+
+            let mut travel = NoCollisionFree::new();
+            let mut cost_flow = SuccessiveShortestPaths::new();
+            travel.prep_sum(&map, &mut agents.clone(), &targets, &mut Dinic::new(), &mut cost_flow, Objective::TotalTravel).unwrap();
+            assert_eq!(horizon(&travel), horizon(&makespan));
+            assert_eq!(cost_flow.get_cost(), moves(&travel) as i64);
+            assert!(moves(&travel) <= moves(&makespan));
+        }
+    }
 
     #[test]
-    fn test_conv() {
+    fn no_collision_free_infeasible_tunnel() {
+        // four agents have to squeeze through the one cell wide tunnel in the middle
+        let map = Map::new("resources/maps/tunnel.map");
+        let agents = agents_from(&vec![Point{x: 10, y: 1}, Point{x: 10, y: 2}, Point{x: 10, y: 3}, Point{x: 9, y: 2}]);
+        let mut targets = targets_from(&vec![Point{x: 18, y: 1}, Point{x: 18, y: 2}, Point{x: 18, y: 3}, Point{x: 19, y: 2}], 1);
+        for target in targets.iter_mut() {
+            target.path = Some(vec![target.position]);
+        }
+
+        let mut strat = NoCollisionFree::new();
+        strat.max_horizon = Some(10);
+        let PlanError::Infeasible(err) = strat.prep(&map, &mut agents.clone(), &targets, &mut Dinic::new()).unwrap_err()
+            else { panic!("expected a cut"); };
+        // every target is close enough on its own, but only one agent per turn enters the tunnel
+        assert_eq!(err.caught, 3);
+        assert!(err.unreachable.is_empty());
+        assert_eq!(err.bottlenecks, vec![Point{x: 12, y: 2}]);
+        assert!(err.corridors.is_empty());
+
+        let mut strat = NoCollisionFree::new();
+        strat.max_horizon = Some(5);
+        let PlanError::Infeasible(err) = strat.prep(&map, &mut agents.clone(), &targets, &mut Dinic::new()).unwrap_err()
+            else { panic!("expected a cut"); };
+        // read off the network, not guessed from the matching
+        assert_eq!(err.horizon, 5);
+        assert_eq!(err.caught, 0);
+        assert_eq!(err.unreachable, vec![0, 1, 2, 3]);
+
+        // two targets are only reachable by the first agent in time
+        let mut strat = NoCollisionFree::new();
+        strat.max_horizon = Some(9);
+        let PlanError::Infeasible(err) = strat.prep(&map, &mut agents_from(&vec![Point{x: 10, y: 2}, Point{x: 1, y: 1}]),
+                                                    &targets[..2], &mut Dinic::new()).unwrap_err()
+            else { panic!("expected a cut"); };
+        assert_eq!(err.caught, 1);
+        assert!(err.unreachable.is_empty());
+
+        let err = strat.prep(&map, &mut agents.clone(), &targets[..3], &mut Dinic::new()).unwrap_err();
+        assert_eq!(err, PlanError::SurplusAgents { agents: 4, targets: 3 });
+
         let mut strat = NoCollisionFree::new();
-        strat.height = 10;
-        strat.width = 10;
+        strat.prep(&map, &mut agents.clone(), &targets, &mut Dinic::new()).unwrap();
+        assert_eq!(strat.paths.iter().map(|x| x.len()).max(), Some(11));
+    }
 
-        let got = strat.conv_expl(0, 3, 5);
-        let reconv = strat.reconv_point(0, got);
-        assert_eq!(Point{x:3, y:5}, reconv);
+    // replays a plan and reports the first wall or off-grid move, vertex conflict or
+    // swap; agents leave the grid when they catch their target (end of their path)
+    fn check_plan(map: &Map, agents: &[Agent], targets: &[Target], paths: &[Vec<Direction>]) -> Result<(), String> {
+        let n = agents.len();
+        let mut pos = agents.iter().map(|x| x.position).collect::<Vec<_>>();
+        let horizon = paths.iter().map(|x| x.len()).max().unwrap_or(0);
+        for time in 0..horizon {
+            let mut next = pos.clone();
+            for (i, path) in paths.iter().enumerate() {
+                if time >= path.len() { continue; }
+                if !map.valid_direction(pos[i], path[time]) || !map.valid_point(&go_direction(pos[i], path[time])) {
+                    return Err(format!("agent {} moves {:?} from {:?} at time {}", i, path[time], pos[i], time));
+                }
+                next[i] = go_direction(pos[i], path[time]);
+            }
+            for i in 0..n {
+                for j in i+1..n {
+                    if time >= paths[i].len() || time >= paths[j].len() { continue; }
+                    if next[i] == next[j] {
+                        return Err(format!("agents {} and {} meet at {:?} at time {}", i, j, next[i], time+1));
+                    }
+                    if next[i] == pos[j] && next[j] == pos[i] && pos[i] != pos[j] {
+                        return Err(format!("agents {} and {} swap {:?} and {:?} at time {}", i, j, pos[i], pos[j], time));
+                    }
+                }
+            }
+            pos = next;
+        }
 
-        let got = strat.conv_expl(4, 3, 5);
-        let reconv = strat.reconv_point(4, got);
-        assert_eq!(Point{x:3, y:5}, reconv);
+        let mut caught = HashSet::new();
+        for (i, agent) in agents.iter().enumerate() {
+            let target = &targets[agent.targets as usize];
+            if pos[i] != target.at_time(paths[i].len()) || !caught.insert(agent.targets) {
+                return Err(format!("agent {} does not catch target {}", i, agent.targets));
+            }
+        }
+        Ok(())
+    }
+
+    // Err(reason) when NoCollisionFree returns a broken plan, infeasible instances are fine
+    fn plan_error(map: &Map, agents: &[Agent], targets: &[Target], max_horizon: Option<usize>) -> Result<(), String> {
+        let mut strat = NoCollisionFree::new();
+        strat.max_horizon = max_horizon;
+        let mut planned = agents.to_vec();
+        if strat.prep(map, &mut planned, targets, &mut Dinic::new()).is_err() { return Ok(()); }
+        check_plan(map, &planned, targets, &strat.paths)?;
+
+        let mut strat = NoCollisionFree::new();
+        strat.max_horizon = max_horizon;
+        let mut planned = agents.to_vec();
+        strat.prep_sum(map, &mut planned, targets, &mut Dinic::new(), &mut SuccessiveShortestPaths::new(),
+                       Objective::TotalTravel).unwrap();
+        check_plan(map, &planned, targets, &strat.paths)
+    }
+
+    // drops agents, targets and the end of target paths as long as the plan stays broken
+    fn minimise(map: &Map, mut agents: Vec<Agent>, mut targets: Vec<Target>,
+                max_horizon: Option<usize>) -> (Vec<Agent>, Vec<Target>) {
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for i in 0..agents.len() {
+                let mut fewer = agents.clone();
+                fewer.remove(i);
+                if !fewer.is_empty() && plan_error(map, &fewer, &targets, max_horizon).is_err() {
+                    agents = fewer;
+                    changed = true;
+                    break;
+                }
+            }
+            for i in 0..targets.len() {
+                let mut fewer = targets.clone();
+                fewer.remove(i);
+                for (idx, target) in fewer.iter_mut().enumerate() { target.idx = idx; }
+                if fewer.len() >= agents.len() && plan_error(map, &agents, &fewer, max_horizon).is_err() {
+                    targets = fewer;
+                    changed = true;
+                    break;
+                }
+            }
+            for i in 0..targets.len() {
+                let mut shorter = targets.clone();
+                let path = shorter[i].path.as_mut().unwrap();
+                if path.len() == 1 { continue; }
+                path.pop();
+                if plan_error(map, &agents, &shorter, max_horizon).is_err() {
+                    targets = shorter;
+                    changed = true;
+                }
+            }
+        }
+        (agents, targets)
+    }
+
+    // side of the windows maps too big for the distance oracle are tested on
+    const WINDOW: usize = 24;
+
+    // WINDOW x WINDOW part of map_name with the bottom left corner at (x, y),
+    // cells on the border of the window become walls
+    fn window_map(map_name: &str, x: usize, y: usize) -> Map {
+        let text = std::fs::read_to_string(format!("resources/maps/{}", map_name)).unwrap();
+        let lines = text.lines().collect::<Vec<_>>();
+        let height = lines[1].split_whitespace().nth(1).unwrap().parse::<usize>().unwrap();
+        let mut window = format!("type octile\nheight {}\nwidth {}\nmap\n", WINDOW, WINDOW);
+        for row in lines[4+height-y-WINDOW..4+height-y].iter() {
+            window += &row[x..x+WINDOW];
+            window += "\n";
+        }
+        let file_path = std::env::temp_dir().join(format!("{}_{}_{}_{}.map", map_name.trim_end_matches(".map"),
+                                                          x, y, std::process::id()));
+        std::fs::write(&file_path, window).unwrap();
+        let map = Map::new(file_path.to_str().unwrap());
+        std::fs::remove_file(&file_path).unwrap();
+        map
+    }
 
-        let pnt = Point{x:7, y:3};
-        let got = strat.conv(3, &pnt);
-        let reconv = strat.reconv_point(3, got);
-        assert_eq!(pnt, reconv);
+    // where a counterexample comes from
+    struct Source {
+        map_name: String,
+        window: Option<(usize, usize)>,
+        max_horizon: Option<usize>,
+    }
+
+    impl Source {
+        fn map(&self) -> Map {
+            match self.window {
+                Some((x, y)) => window_map(&self.map_name, x, y),
+                None => Map::new(&format!("resources/maps/{}", self.map_name)),
+            }
+        }
+    }
+
+    // map <name> [window <x> <y>] [horizon <max horizon>],
+    // agent <x> <y>, target <x> <y> <x> <y>... (path)
+    fn counterexample(source: &Source, agents: &[Agent], targets: &[Target]) -> String {
+        let mut text = format!("map {}", source.map_name);
+        if let Some((x, y)) = source.window {
+            text += &format!(" window {} {}", x, y);
+        }
+        if let Some(horizon) = source.max_horizon {
+            text += &format!(" horizon {}", horizon);
+        }
+        text += "\n";
+        for agent in agents.iter() {
+            text += &format!("agent {} {}\n", agent.position.x, agent.position.y);
+        }
+        for target in targets.iter() {
+            let path = target.path.as_ref().unwrap().iter()
+                .map(|p| format!("{} {}", p.x, p.y))
+                .collect::<Vec<_>>();
+            text += &format!("target {}\n", path.join(" "));
+        }
+        text
+    }
+
+    fn load_counterexample(file_path: &str) -> (Source, Vec<Agent>, Vec<Target>) {
+        let text = std::fs::read_to_string(file_path).unwrap();
+        let mut source = Source { map_name: String::new(), window: None, max_horizon: None };
+        let mut agents = Vec::new();
+        let mut targets = Vec::new();
+        for line in text.lines() {
+            let words = line.split_whitespace().collect::<Vec<_>>();
+            let points = || words[1..].chunks(2)
+                .map(|x| Point{x: x[0].parse().unwrap(), y: x[1].parse().unwrap()})
+                .collect::<Vec<_>>();
+            match words[0] {
+                "map" => {
+                    source.map_name = words[1].to_string();
+                    let number = |idx: usize| words[idx].parse::<usize>().unwrap();
+                    let mut idx = 2;
+                    while idx < words.len() {
+                        match words[idx] {
+                            "window" => {
+                                source.window = Some((number(idx+1), number(idx+2)));
+                                idx += 3;
+                            },
+                            "horizon" => {
+                                source.max_horizon = Some(number(idx+1));
+                                idx += 2;
+                            },
+                            _ => panic!("unknown map option: {}", line),
+                        }
+                    }
+                },
+                "agent" => agents.push(points()[0]),
+                "target" => {
+                    let points = points();
+                    let mut target = targets_from(&vec![points[0]], 1).pop().unwrap();
+                    target.idx = targets.len();
+                    target.path = Some(points);
+                    targets.push(target);
+                },
+                _ => panic!("unknown line: {}", line),
+            }
+        }
+        (source, agents_from(&agents), targets)
+    }
+
+    #[test]
+    fn no_collision_free_plans_are_valid() {
+        let mut rng = rand::thread_rng();
+        let mut maps = std::fs::read_dir("resources/maps").unwrap()
+            .map(|x| x.unwrap().file_name().into_string().unwrap())
+            .filter(|x| x.ends_with(".map"))
+            .collect::<Vec<_>>();
+        maps.sort();
+        for map_name in maps {
+            let header = std::fs::read_to_string(format!("resources/maps/{}", map_name)).unwrap();
+            let sides = header.lines().skip(1).take(2)
+                .map(|l| l.split_whitespace().nth(1).unwrap().parse::<usize>().unwrap())
+                .collect::<Vec<_>>();
+            let (height, width) = (sides[0], sides[1]);
+            // distance oracle of bigger maps takes gigabytes, they are tested on random windows
+            let windowed = height*width > 5000;
+            let whole = if windowed { None } else { Some(Map::new(&format!("resources/maps/{}", map_name))) };
+            // the planner is slow in debug builds when agents are far apart
+            let max_horizon = if !windowed && height*width > 1000 { Some(20) } else { None };
+            let runs = if windowed { 16 } else if height*width > 1000 { 6 } else { 40 };
+
+            for run in 0..runs {
+                let mut source = Source { map_name: map_name.clone(), window: None, max_horizon };
+                let map = match &whole {
+                    Some(map) => map,
+                    None => {
+                        source.window = Some((rng.gen_range(0..=width-WINDOW), rng.gen_range(0..=height-WINDOW)));
+                        &source.map()
+                    },
+                };
+                let n = rng.gen_range(1..=4);
+                let Ok((mut all_agents, mut all_targets)) = gen_set(map, 1, 3, n, n, &mut rng, Vec::new(), Vec::new())
+                    else { continue; };
+                let agents = all_agents.pop().unwrap();
+                let mut targets = all_targets.pop().unwrap();
+                let _ = TargetFollowPath::new(n, map, targets.iter().map(|x| x.position).collect(),
+                                              &mut targets, true, PathLength::Fixed(15), SAME_DIR);
+
+                if plan_error(map, &agents, &targets, max_horizon).is_ok() { continue; }
+                let (agents, targets) = minimise(map, agents, targets, max_horizon);
+                let text = counterexample(&source, &agents, &targets);
+                // to be copied to resources/counterexamples
+                if let Ok(dir) = std::env::var("COUNTEREXAMPLES_DIR") {
+                    let file_path = format!("{}/{}_{}.txt", dir, map_name.trim_end_matches(".map"), run);
+                    std::fs::create_dir_all(&dir).unwrap();
+                    std::fs::write(&file_path, &text).unwrap();
+                }
+                panic!("{}\n{}", plan_error(map, &agents, &targets, max_horizon).unwrap_err(), text);
+            }
+        }
+    }
+
+    // minimised counterexamples found by no_collision_free_plans_are_valid
+    #[test]
+    fn no_collision_free_counterexamples() {
+        let Ok(dir) = std::fs::read_dir("resources/counterexamples") else { return; };
+        let mut paths = dir
+            .map(|x| x.unwrap().path().to_str().unwrap().to_string())
+            .collect::<Vec<_>>();
+        paths.sort();
+        for file_path in paths {
+            let (source, agents, targets) = load_counterexample(&file_path);
+            assert_eq!(plan_error(&source.map(), &agents, &targets, source.max_horizon), Ok(()), "{}", file_path);
+        }
+    }
+
+    #[test]
+    fn expected_capture_intercepts_predictable_target() {
+        let map = Map::new("resources/maps/box.map");
+        // always east, stops at the wall
+        let mut weights = [[0.0; 5]; 5];
+        for row in weights.iter_mut() { row[1] = 1.0; }
+        let agents = agents_from(&vec![Point{x: 6, y: 1}]);
+        let targets = targets_from(&vec![Point{x: 1, y: 4}], 100);
+        let model = MarkovTarget::new(MarkovModel::PerHeading(weights), 100);
+        let mut runner = Runner { map: &map, agents, targets, d_time: 100, observation: None };
+        let mut target_strat: Box<dyn TargetStrategy> = Box::new(MarkovTarget::new(MarkovModel::PerHeading(weights), 100));
+        let turns = runner.run(Box::new(ExpectedCapture::new(Box::new(model), 10)), &mut target_strat,
+                               false, false, false, false, "", 100);
+        // meets the target at (5, 4)
+        assert_eq!(turns, 4);
+    }
+
+    #[test]
+    fn expected_capture_scores_arrival_window() {
+        let map = Map::new("resources/maps/box.map");
+        let agent = Point{x: 1, y: 1};
+        let (a, b) = (Point{x: 2, y: 2}, Point{x: 3, y: 1});
+        let mut forecast = vec![HashMap::new(); 4];
+        forecast[2] = HashMap::from([(a, 0.4), (b, 0.3)]);
+        forecast[3] = HashMap::from([(b, 0.3)]);
+        let (pr, turns, cell) = ExpectedCapture::best_cell(&map, agent, &forecast);
+        assert_eq!((cell, turns), (b, -2));
+        assert!((pr-0.6).abs() < 1e-9);
+        // out of reach
+        forecast = vec![HashMap::from([(Point{x: 8, y: 8}, 1.0)]); 3];
+        assert_eq!(ExpectedCapture::best_cell(&map, agent, &forecast).0, 0.0);
+
+        // an assigned agent goes for its own target, the other one heads for the far target
+        let mut agents = agents_from(&vec![Point{x: 4, y: 4}, Point{x: 1, y: 1}]);
+        agents[0].targets = 1;
+        let mut targets = targets_from(&vec![Point{x: 4, y: 5}, Point{x: 8, y: 8}], 3);
+        for (idx, target) in targets.iter_mut().enumerate() { target.idx = idx; }
+        let mut strat = ExpectedCapture::new(Box::new(MarkovTarget::new(MarkovModel::Uniform, 3)), 2);
+        let dirs = strat.pick(&map, &mut agents, &targets);
+        let next = go_direction(agents[0].position, dirs[0]);
+        assert!(map.dist_point(&next, &targets[1].position) < map.dist_point(&agents[0].position, &targets[1].position));
+        assert_ne!(dirs[1], Direction::None);
+    }
+
+    #[test]
+    fn expected_capture_random_targets() {
+        let map = Map::new("resources/maps/box.map");
+        let d_time = 3;
+        for _ in 0..10 {
+            let mut agents = agents_random(&map, 5);
+            let mut targets = targets_from(&agents.split_off(3).iter().map(|x| x.position).collect(), d_time);
+            for (idx, target) in targets.iter_mut().enumerate() { target.idx = idx; }
+            let model = MarkovTarget::new(MarkovModel::Uniform, d_time);
+            let mut runner = Runner { map: &map, agents, targets, d_time, observation: None };
+            let mut target_strat: Box<dyn TargetStrategy> = Box::new(RandomTarget {});
+            runner.run(Box::new(ExpectedCapture::new(Box::new(model), 8)), &mut target_strat,
+                       false, false, false, false, "", 500);
+            assert!(runner.targets.is_empty());
+        }
     }
 }
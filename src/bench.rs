@@ -1,19 +1,40 @@
-use crate::{agent_strategies::*, flow::*, hopcroft_karp::HopcroftKarp, map::*, matching::*, runner::*, target_strategies::*, TurboMatching};
+use crate::{agent_strategies::*, flow::*, game::GameSolver, hopcroft_karp::HopcroftKarp, map::*, matching::*, runner::*, target_strategies::*, TurboMatching};
+use crate::observation::ObservationModel;
+use std::collections::HashMap;
+use std::rc::Rc;
 use std::time::Instant;
 use rand::{rngs::ThreadRng, Rng};
 use tqdm::tqdm;
 
+// exact game solutions take long to find, runs on the same map share them,
+// keyed by (number of agents, d_time)
+pub type GameSolvers = HashMap<(usize, i32), Rc<GameSolver>>;
+
+fn game_solver(solvers: &mut GameSolvers, map: &Map, agents: usize, d_time: i32) -> Result<Rc<GameSolver>, String> {
+    if let Some(solver) = solvers.get(&(agents, d_time)) {
+        return Ok(solver.clone());
+    }
+    let solver = Rc::new(GameSolver::new(map, agents, d_time)?);
+    solvers.insert((agents, d_time), solver.clone());
+    Ok(solver)
+}
+
 pub struct AgentStrategyTemplate {
     pub strategy: AgentStrategies,
     pub permutation: Option<Vec<usize>>,
     pub matcher: Option<HopcroftKarp>, // TODO: replace with traits later
-    pub flow: Option<FordFulkerson>,
+    pub flow: Option<FlowBackends>,
+    pub objective: Objective,
+    // target motion known to ExpectedCapture
+    pub motion: MarkovModel,
 }
 
 impl AgentStrategyTemplate {
-    fn construct(&self, map: &Map, agents: &mut Vec<Agent>, targets: &Vec<Target>) -> Box<dyn AgentStrategy> {
-        match self.strategy {
-            AgentStrategies::MakeSpanHopcroft => Box::new(MakeSpanHopcroft {}),
+    fn construct(&self, map: &Map, agents: &mut Vec<Agent>, targets: &Vec<Target>, d_time: i32,
+                 solvers: &mut GameSolvers) -> Result<Box<dyn AgentStrategy>, String> {
+        Ok(match self.strategy {
+            AgentStrategies::MakeSpanHopcroft => Box::new(MakeSpanHopcroft::new()),
+            AgentStrategies::Pibt => Box::new(Pibt::new()),
             AgentStrategies::NoCollisionSingle => {
                 let mut res = NoCollisionSingle::new();
                 res.prep(map, &agents[0], &targets[0]);
@@ -26,35 +47,115 @@ impl AgentStrategyTemplate {
             },
             AgentStrategies::CollisionFree => {
                 let mut res = CollisionFree::new();
-                res.prep(map, agents, targets, &mut self.matcher.clone().unwrap());
+                match self.objective {
+                    Objective::Makespan => res.prep(map, agents, targets, &mut self.matcher.clone().unwrap()),
+                    _ => res.prep_sum(map, agents, targets, self.objective),
+                }
                 Box::new(res)
             },
+            AgentStrategies::Optimal => Box::new(OptimalAgents::new(game_solver(solvers, map, agents.len(), d_time)?)),
+            AgentStrategies::ExpectedCapture => Box::new(ExpectedCapture::new(Box::new(
+                MarkovTarget::new(self.motion.clone(), d_time)), 20)),
             AgentStrategies::NoCollisionFree => {
                 let mut res = NoCollisionFree::new();
-                res.prep(map, agents, targets, &mut self.flow.clone().unwrap());
+                match self.flow.unwrap() {
+                    FlowBackends::FordFulkerson => self.prep_flow(&mut res, map, agents, targets, &mut FordFulkerson::new()),
+                    FlowBackends::Dinic => self.prep_flow(&mut res, map, agents, targets, &mut Dinic::new()),
+                    FlowBackends::PushRelabel => self.prep_flow(&mut res, map, agents, targets, &mut PushRelabel::new()),
+                }.map_err(|e| e.to_string())?;
                 Box::new(res)
             },
+        })
+    }
+
+    // backend finds the makespan, other objectives are then optimised with min cost flow
+    fn prep_flow(&self, res: &mut NoCollisionFree, map: &Map, agents: &mut Vec<Agent>, targets: &Vec<Target>,
+                 flow: &mut impl MaxFlow) -> Result<(), PlanError> {
+        match self.objective {
+            Objective::Makespan => res.prep(map, agents, targets, flow),
+            _ => res.prep_sum(map, agents, targets, flow, &mut SuccessiveShortestPaths::new(), self.objective),
         }
     }
 }
 
 pub struct TargetStrategyTemplate {
     pub strategy: TargetStrategies,
+    // generated paths of TargetFollowPath
+    pub path_length: PathLength,
+    pub same_dir: f64,
+    // cycles of Patrol, its precomputed paths are path_length long
+    pub waypoints: Vec<Vec<Point>>,
+    pub motion: MarkovModel,
 }
 
 impl TargetStrategyTemplate {
-    fn construct(&self, map: &Map, agents: &Vec<Agent>, targets: &mut Vec<Target>) -> Box<dyn TargetStrategy> {
-        match self.strategy {
+    pub fn construct(&self, map: &Map, agents: &[Agent], targets: &mut Vec<Target>, d_time: i32,
+                 solvers: &mut GameSolvers) -> Result<Box<dyn TargetStrategy>, String> {
+        Ok(match self.strategy {
             TargetStrategies::RandomTarget => Box::new(RandomTarget {}),
             TargetStrategies::MaximizeMinDist => Box::new(MaximizeMinDist {}),
             TargetStrategies::TargetFollowPath => {
-                // TODO: move true and 100 to config
-                let mut res = TargetFollowPath::new(targets.len(), map,
-                    targets.iter().map(|x| x.position).collect(), targets, true, 10);
+                let res = TargetFollowPath::new(targets.len(), map,
+                    targets.iter().map(|x| x.position).collect(), targets, true, self.path_length, self.same_dir);
                 Box::new(res)
             },
+            TargetStrategies::Evader => Box::new(Evader::new(2, d_time)),
+            TargetStrategies::Optimal => Box::new(OptimalTarget::new(game_solver(solvers, map, agents.len(), d_time)?)),
+            TargetStrategies::Patrol => Box::new(Patrol::new(map, self.waypoints.clone(), targets, d_time,
+                                                             self.path_length.sample(&mut rand::thread_rng()))?),
+            TargetStrategies::Markov => Box::new(MarkovTarget::new(self.motion.clone(), d_time)),
+        })
+    }
+}
+
+pub struct FlowBenchmarkResult {
+    pub backend: FlowBackends,
+    pub avg_time: f64,
+    pub flows: Vec<i32>,
+}
+
+fn time_flow(flow: &mut impl MaxFlow, map: &Map, horizon: usize, agents: &[Agent], targets: &[Target]) -> (i32, f64) {
+    NoCollisionFree::new().network(flow, horizon, map, agents, targets);
+    let start_time = Instant::now();
+    let got = flow.get_flow();
+    (got, start_time.elapsed().as_secs_f64()*1000.0)
+}
+
+// solves the same time expanded networks (as built by NoCollisionFree) with every backend
+// targets need generated paths, construction time is not measured
+pub fn bench_flow(map: &Map, horizon: usize, all_agents: &[Vec<Agent>], all_targets: &[Vec<Target>],
+                  backends: &[FlowBackends]) -> Result<Vec<FlowBenchmarkResult>, String> {
+
+    let mut results: Vec<FlowBenchmarkResult> = Vec::new();
+    for backend in backends.iter() {
+        let mut sum_time = 0.0;
+        let mut flows = Vec::new();
+        for run_id in tqdm(0..all_agents.len()) {
+            let agents = &all_agents[run_id];
+            let targets = &all_targets[run_id];
+            let (got, took) = match backend {
+                FlowBackends::FordFulkerson => time_flow(&mut FordFulkerson::new(), map, horizon, agents, targets),
+                FlowBackends::Dinic => time_flow(&mut Dinic::new(), map, horizon, agents, targets),
+                FlowBackends::PushRelabel => time_flow(&mut PushRelabel::new(), map, horizon, agents, targets),
+            };
+            if let Some(prev) = results.first() {
+                if prev.flows[run_id] != got {
+                    return Err(format!("backends disagree on run {}: {:?}={}, {:?}={}",
+                                       run_id, prev.backend, prev.flows[run_id], backend, got));
+                }
+            }
+            sum_time += took;
+            flows.push(got);
         }
+
+        results.push(FlowBenchmarkResult {
+            backend: *backend,
+            avg_time: sum_time/(all_agents.len() as f64),
+            flows,
+        });
     }
+
+    Ok(results)
 }
 
 pub struct BenchmarkResult {
@@ -146,26 +247,29 @@ pub fn gen_set(map: &Map, nruns: usize, d_time: i32, num_agents: usize, num_targ
 
 pub fn bench(map: &Map, num_runs: i32, d_time: i32, all_agents: Vec<Vec<Agent>>, all_targets: Vec<Vec<Target>>,
              agent_strat_template: AgentStrategyTemplate, target_strat: &mut Vec<Box<dyn TargetStrategy>>,
-             debug_print: bool, collect_individual: bool
+             observation: Option<ObservationModel>, debug_print: bool, collect_individual: bool
             ) -> Result<BenchmarkResult, String> {
 
     let mut sum_length: u64 = 0;
     let mut sum_time: u128 = 0;
     let mut all_results = Vec::new();
+    let mut solvers = GameSolvers::new();
     for run_id in tqdm(0..num_runs as usize) {
         let start_time = Instant::now();
 
         let mut agents = all_agents[run_id].clone();
         let targets = all_targets[run_id].clone();
 
-        let agent_strat = agent_strat_template.construct(&map, &mut agents, &targets);
+        let agent_strat = agent_strat_template.construct(map, &mut agents, &targets, d_time, &mut solvers)?;
 
         let mut runner = Runner {
             map,
             agents,
             targets,
-            d_time
+            d_time,
+            observation,
         };
+        runner.check(agent_strat.as_ref())?;
 
         // println!("starting runner: {}", iter);
         // println!("{:?}", agents);
@@ -198,3 +302,37 @@ pub fn bench(map: &Map, num_runs: i32, d_time: i32, all_agents: Vec<Vec<Agent>>,
         }
     );
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn game_solvers_are_shared() {
+        let map = Map::new("resources/maps/example.map");
+        let mut solvers = GameSolvers::new();
+        let first = game_solver(&mut solvers, &map, 1, 2).unwrap();
+        assert!(Rc::ptr_eq(&first, &game_solver(&mut solvers, &map, 1, 2).unwrap()));
+        assert!(!Rc::ptr_eq(&first, &game_solver(&mut solvers, &map, 1, 3).unwrap()));
+        assert_eq!(solvers.len(), 2);
+    }
+
+    #[test]
+    fn flow_backends_agree() {
+        let map = Map::new("resources/maps/tunnel.map");
+        let (all_agents, mut all_targets) = gen_set(&map, 5, 5, 3, 3, &mut rand::thread_rng(),
+                                                    Vec::new(), Vec::new()).unwrap();
+        for targets in all_targets.iter_mut() {
+            let _ = TargetFollowPath::new(targets.len(), &map, targets.iter().map(|x| x.position).collect(),
+                                          targets, true, PathLength::Fixed(30), SAME_DIR);
+        }
+
+        let backends = vec![FlowBackends::FordFulkerson, FlowBackends::Dinic, FlowBackends::PushRelabel];
+        let results = bench_flow(&map, 20, &all_agents, &all_targets, &backends).unwrap();
+        assert_eq!(results.len(), 3);
+        for result in results.iter() {
+            assert_eq!(result.flows.len(), 5);
+            assert!(result.flows.iter().all(|x| *x <= 3));
+        }
+    }
+}
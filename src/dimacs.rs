@@ -0,0 +1,148 @@
+use std::collections::HashMap;
+use std::fs;
+use crate::flow::MaxFlow;
+
+// DIMACS max flow format (http://archive.dimacs.rutgers.edu/pub/netflow/general-info/)
+// c comment
+// p max <nodes> <arcs>
+// n <id> s
+// n <id> t
+// a <from> <to> <capacity>
+// vertex ids are dense and 1-based
+
+// writes the network (capacities only, flow is dropped), ids are remapped
+// to 1..=n in increasing order of the original ids
+pub fn to_dimacs(flow: &impl MaxFlow) -> String {
+    let edges = flow.get_edges();
+    let mut ids = edges.iter()
+        .flat_map(|e| [e.0, e.1])
+        .chain([flow.get_source(), flow.get_sink()])
+        .collect::<Vec<_>>();
+    ids.sort();
+    ids.dedup();
+    let dense = ids.iter()
+        .enumerate()
+        .map(|(idx, id)| (*id, idx+1))
+        .collect::<HashMap<_, _>>();
+
+    let mut res = String::new();
+    res += &format!("p max {} {}\n", ids.len(), edges.len());
+    res += &format!("n {} s\n", dense[&flow.get_source()]);
+    res += &format!("n {} t\n", dense[&flow.get_sink()]);
+    for (from, to, capacity, _) in edges.iter() {
+        res += &format!("a {} {} {}\n", dense[from], dense[to], capacity);
+    }
+    res
+}
+
+// resets flow and fills it with the network, ids are kept as in the file
+pub fn from_dimacs(text: &str, flow: &mut impl MaxFlow) -> Result<(), String> {
+    flow.reset();
+    let mut nodes = None;
+    let mut source = None;
+    let mut sink = None;
+    for (idx, line) in text.lines().enumerate() {
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        let err = |msg: &str| format!("line {}: {}: {}", idx+1, msg, line);
+        let number = |word: &str| word.parse::<i32>().map_err(|_| err("expected a number"));
+        let vertex = |word: &str| {
+            let id = number(word)?;
+            match nodes {
+                None => Err(err("missing problem line")),
+                Some(n) if id < 1 || id > n => Err(err("vertex out of range")),
+                _ => Ok(id),
+            }
+        };
+
+        match words.as_slice() {
+            [] | ["c", ..] => {},
+            ["p", "max", n, _] => nodes = Some(number(n)?),
+            ["n", id, "s"] => source = Some(vertex(id)?),
+            ["n", id, "t"] => sink = Some(vertex(id)?),
+            ["a", from, to, capacity] => flow.add_edge(vertex(from)?, vertex(to)?, number(capacity)?),
+            _ => return Err(err("unknown line")),
+        }
+    }
+
+    flow.set_source(source.ok_or("missing source")?);
+    flow.set_sink(sink.ok_or("missing sink")?);
+    Ok(())
+}
+
+pub fn save_dimacs(flow: &impl MaxFlow, file_path: &str) -> Result<(), String> {
+    fs::write(file_path, to_dimacs(flow)).map_err(|e| format!("{}: {}", file_path, e))
+}
+
+#[allow(dead_code)]
+pub fn load_dimacs(file_path: &str, flow: &mut impl MaxFlow) -> Result<(), String> {
+    let text = fs::read_to_string(file_path).map_err(|e| format!("{}: {}", file_path, e))?;
+    from_dimacs(&text, flow).map_err(|e| format!("{}: {}", file_path, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use crate::dimacs::*;
+    use crate::flow::*;
+
+    #[test]
+    fn round_trip() {
+        let mut flow = Dinic::new();
+        flow.set_source(-1);
+        flow.set_sink(-2);
+        flow.add_edge(-1, 10, 3);
+        flow.add_edge(-1, 4, 2);
+        flow.add_edge(10, 4, 1);
+        flow.add_edge(10, -2, 2);
+        flow.add_edge(4, -2, 3);
+        let text = to_dimacs(&flow);
+        assert_eq!(text, "p max 4 5\nn 2 s\nn 1 t\na 2 4 3\na 2 3 2\na 4 3 1\na 4 1 2\na 3 1 3\n");
+
+        let mut loaded = Dinic::new();
+        from_dimacs(&text, &mut loaded).unwrap();
+        assert_eq!(loaded.get_flow(), flow.get_flow());
+        assert_eq!(to_dimacs(&loaded), text);
+    }
+
+    #[test]
+    fn errors() {
+        let mut flow = Dinic::new();
+        assert!(from_dimacs("a 1 2 3\n", &mut flow).is_err());
+        assert!(from_dimacs("p max 2 1\nn 1 s\nn 2 t\na 1 3 1\n", &mut flow).is_err());
+        assert!(from_dimacs("p max 2 1\nn 1 s\na 1 2 1\n", &mut flow).is_err());
+        assert!(from_dimacs("p max 2 1\nn 1 s\nn 2 t\na 1 2 x\n", &mut flow).is_err());
+        assert!(from_dimacs("c only\np max 2 1\nn 1 s\nn 2 t\n\na 1 2 4\n", &mut flow).is_ok());
+        assert_eq!(flow.get_flow(), 4);
+    }
+
+    // stored instances, "c flow <value>" gives the expected max flow
+    #[test]
+    fn stored_instances() {
+        let mut paths = fs::read_dir("resources/dimacs").unwrap()
+            .map(|x| x.unwrap().path())
+            .collect::<Vec<_>>();
+        paths.sort();
+        assert!(!paths.is_empty());
+        for path in paths {
+            let path = path.to_str().unwrap();
+            let text = fs::read_to_string(path).unwrap();
+            let exp = text.lines()
+                .find_map(|l| l.strip_prefix("c flow "))
+                .map(|x| x.trim().parse::<i32>().unwrap())
+                .unwrap();
+
+            let mut ff = FordFulkerson::new();
+            load_dimacs(path, &mut ff).unwrap();
+            assert_eq!(ff.get_flow(), exp, "{}", path);
+            let mut dinic = Dinic::new();
+            load_dimacs(path, &mut dinic).unwrap();
+            assert_eq!(dinic.get_flow(), exp, "{}", path);
+            let mut pr = PushRelabel::new();
+            load_dimacs(path, &mut pr).unwrap();
+            assert_eq!(pr.get_flow(), exp, "{}", path);
+            let mut ssp = SuccessiveShortestPaths::new();
+            load_dimacs(path, &mut ssp).unwrap();
+            assert_eq!(ssp.get_flow(), exp, "{}", path);
+        }
+    }
+}
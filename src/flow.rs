@@ -1,4 +1,5 @@
-use std::collections::{HashMap, HashSet};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
 
 pub trait MaxFlow {
     fn new() -> Self;
@@ -8,13 +9,137 @@ pub trait MaxFlow {
     fn get_flow(&mut self) -> i32;
     fn set_source(&mut self, source: i32);
     fn set_sink(&mut self, sink: i32);
+    fn get_source(&self) -> i32;
+    fn get_sink(&self) -> i32;
     // return any saturated edge! <if exists>
+    #[allow(dead_code)]
     fn get_saturated_edge(&self, vertex: i32) -> Option<(i32, i32)>;
+    // (from, to, capacity, flow) for every added edge
+    fn get_edges(&self) -> Vec<(i32, i32, i32, i32)>;
+
+    // splits the current flow into source->sink paths with their flow values,
+    // cycles of flow are cancelled on the way
+    fn decompose(&self) -> Vec<(Vec<i32>, i32)> {
+        let source = self.get_source();
+        let sink = self.get_sink();
+        let mut out: HashMap<i32, Vec<(i32, i32)>> = HashMap::new();
+        for (from, to, _, flow) in self.get_edges() {
+            if flow > 0 { out.entry(from).or_default().push((to, flow)); }
+        }
+
+        let mut res = Vec::new();
+        loop {
+            let mut path = vec![source];
+            // (vertex, index in out[vertex]) of every edge on the path
+            let mut used: Vec<(i32, usize)> = Vec::new();
+            let mut on_path = HashMap::from([(source, 0)]);
+            while *path.last().unwrap() != sink {
+                let v = *path.last().unwrap();
+                let edges = out.entry(v).or_default();
+                while edges.last().is_some_and(|e| e.1 == 0) { edges.pop(); }
+                let Some(&(u, _)) = edges.last() else { break; };
+                used.push((v, edges.len()-1));
+
+                if let Some(&pos) = on_path.get(&u) {
+                    let amount = used[pos..].iter().map(|&(w, e)| out[&w][e].1).min().unwrap();
+                    for &(w, e) in used[pos..].iter() {
+                        out.get_mut(&w).unwrap()[e].1 -= amount;
+                    }
+                    for w in path.drain(pos+1..) { on_path.remove(&w); }
+                    used.truncate(pos);
+                    continue;
+                }
+                on_path.insert(u, path.len());
+                path.push(u);
+            }
+            // no flow left (or flow is not conserved)
+            if *path.last().unwrap() != sink { break; }
+
+            let amount = used.iter().map(|&(w, e)| out[&w][e].1).min().unwrap();
+            for &(w, e) in used.iter() {
+                out.get_mut(&w).unwrap()[e].1 -= amount;
+            }
+            res.push((path, amount));
+        }
+        res
+    }
+
+    // vertices reachable from the source in the residual network
+    fn residual_reachable(&self) -> HashSet<i32> {
+        let mut residual: HashMap<i32, Vec<i32>> = HashMap::new();
+        for (from, to, capacity, flow) in self.get_edges() {
+            if flow < capacity { residual.entry(from).or_default().push(to); }
+            if flow > 0 { residual.entry(to).or_default().push(from); }
+        }
+
+        let mut reached = HashSet::from([self.get_source()]);
+        let mut q = VecDeque::from([self.get_source()]);
+        while let Some(v) = q.pop_front() {
+            for u in residual.get(&v).into_iter().flatten() {
+                if reached.insert(*u) { q.push_back(*u); }
+            }
+        }
+        reached
+    }
+
+    // edges of a minimum cut for the current (maximum) flow,
+    // source side is everything reachable from the source in the residual network
+    fn min_cut(&self) -> Vec<(i32, i32)> {
+        let reached = self.residual_reachable();
+        self.get_edges().iter()
+            .filter(|e| reached.contains(&e.0) && !reached.contains(&e.1) && e.2 > 0)
+            .map(|e| (e.0, e.1))
+            .collect()
+    }
+
+    // checks that the current flow is a maximum flow of the given value:
+    // capacities, conservation, source/sink balance and that the sink is
+    // not reachable from the source in the residual network
+    // runs after every solve with the flow-checks feature
+    #[cfg_attr(not(feature = "flow-checks"), allow(dead_code))]
+    fn verify(&self, value: i32) -> Result<(), String> {
+        let source = self.get_source();
+        let sink = self.get_sink();
+        let edges = self.get_edges();
+        let mut balance: HashMap<i32, i64> = HashMap::new();
+        for &(from, to, capacity, flow) in edges.iter() {
+            if flow < 0 || flow > capacity {
+                return Err(format!("edge {}->{} has flow {} with capacity {}", from, to, flow, capacity));
+            }
+            *balance.entry(from).or_insert(0) -= flow as i64;
+            *balance.entry(to).or_insert(0) += flow as i64;
+        }
+        for (v, b) in balance.iter() {
+            if *v != source && *v != sink && *b != 0 {
+                return Err(format!("vertex {} is not balanced: {}", v, b));
+            }
+        }
+        let out = -balance.get(&source).copied().unwrap_or(0);
+        let inc = balance.get(&sink).copied().unwrap_or(0);
+        if out != value as i64 || inc != value as i64 {
+            return Err(format!("flow is {}, but source sends {} and sink gets {}", value, out, inc));
+        }
+        // no augmenting path -> edges leaving the reachable part form a saturated cut
+        if self.residual_reachable().contains(&sink) {
+            return Err(format!("flow {} is not maximum, sink is reachable in the residual network", value));
+        }
+        Ok(())
+    }
+
     // debug functions
+    #[allow(dead_code)]
     fn assert_only_one_saturated(&self);
+    #[allow(dead_code)]
     fn assert_incoming_equals_outgoing(&self);
 }
 
+// max flow of minimum total cost, add_edge adds edges with cost 0
+pub trait MinCostFlow: MaxFlow {
+    fn add_cost_edge(&mut self, from: i32, to: i32, capacity: i32, cost: i64);
+    #[allow(dead_code)]
+    fn get_cost(&mut self) -> i64;
+}
+
 #[derive(Clone)]
 pub struct FordFulkerson {
     flow: i32,
@@ -90,8 +215,9 @@ impl MaxFlow for FordFulkerson {
         self.capacities.insert((to, from), 0);
     }
 
+    // continues from the current flow, so edges can be added between calls
     fn solve(&mut self) {
-        self.flow = 0;
+        if self.flow == -1 { self.flow = 0; }
         loop {
             let mut vis = HashMap::new();
             for v in self.vertices.iter() { vis.insert(*v, false); }
@@ -116,6 +242,9 @@ impl MaxFlow for FordFulkerson {
         //    .filter(|e| *e.1 != 0)
         //    .collect::<HashMap<_, _>>();
         //println!("{:?}", smaller);
+
+        #[cfg(feature = "flow-checks")]
+        if let Err(e) = self.verify(self.flow) { panic!("invalid flow: {}", e); }
     }
 
 
@@ -128,6 +257,8 @@ impl MaxFlow for FordFulkerson {
 
     fn set_source(&mut self, source: i32) { self.source = source; }
     fn set_sink(&mut self, sink: i32) { self.sink = sink; }
+    fn get_source(&self) -> i32 { self.source }
+    fn get_sink(&self) -> i32 { self.sink }
 
     fn get_saturated_edge(&self, vertex: i32) -> Option<(i32, i32)> {
         if self.adj.contains_key(&vertex) {
@@ -139,6 +270,13 @@ impl MaxFlow for FordFulkerson {
         None
     }
 
+    fn get_edges(&self) -> Vec<(i32, i32, i32, i32)> {
+        self.capacities.iter()
+            .filter(|(_, cap)| **cap > 0)
+            .map(|(&(from, to), &cap)| (from, to, cap, self.flows[&(from, to)]))
+            .collect()
+    }
+
     fn assert_only_one_saturated(&self) {
         for v in self.vertices.iter() {
             if *v == self.sink || *v == self.source { continue; }
@@ -174,9 +312,626 @@ impl MaxFlow for FordFulkerson {
     }
 }
 
+// residual network shared by array based solvers
+// vertex ids are mapped to dense indices, edges are stored in flat arrays,
+// edge e and e^1 are forward and residual edge of the same pair
+#[derive(Clone)]
+struct Network {
+    ids: HashMap<i32, usize>,
+    names: Vec<i32>,
+    adj: Vec<Vec<usize>>,
+    to: Vec<usize>,
+    residual: Vec<i32>,
+    capacity: Vec<i32>,
+}
+
+impl Network {
+    fn new() -> Self {
+        Network {
+            ids: HashMap::new(),
+            names: Vec::new(),
+            adj: Vec::new(),
+            to: Vec::new(),
+            residual: Vec::new(),
+            capacity: Vec::new(),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    fn id(&mut self, vertex: i32) -> usize {
+        if let Some(&v) = self.ids.get(&vertex) { return v; }
+        let v = self.names.len();
+        self.ids.insert(vertex, v);
+        self.names.push(vertex);
+        self.adj.push(Vec::new());
+        v
+    }
+
+    fn add_edge(&mut self, from: i32, to: i32, capacity: i32) {
+        let v = self.id(from);
+        let u = self.id(to);
+        self.adj[v].push(self.to.len());
+        self.to.push(u);
+        self.residual.push(capacity);
+        self.capacity.push(capacity);
+        self.adj[u].push(self.to.len());
+        self.to.push(v);
+        self.residual.push(0);
+        self.capacity.push(0);
+    }
+
+    fn push(&mut self, e: usize, amount: i32) {
+        self.residual[e] -= amount;
+        self.residual[e^1] += amount;
+    }
+
+    // flow on edge e (only meaningful for forward edges)
+    fn edge_flow(&self, e: usize) -> i32 {
+        self.capacity[e]-self.residual[e]
+    }
+
+    // net flow leaving v
+    fn outflow(&self, v: usize) -> i32 {
+        self.adj[v].iter().map(|e| self.edge_flow(*e)).sum()
+    }
+
+    #[allow(dead_code)]
+    fn saturated_edge(&self, vertex: i32) -> Option<(i32, i32)> {
+        let v = *self.ids.get(&vertex)?;
+        for &e in self.adj[v].iter() {
+            if e%2 == 0 && self.edge_flow(e) > 0 {
+                return Some((vertex, self.names[self.to[e]]));
+            }
+        }
+        None
+    }
+
+    fn edges(&self) -> Vec<(i32, i32, i32, i32)> {
+        (0..self.to.len()).step_by(2)
+            .map(|e| (self.names[self.to[e^1]], self.names[self.to[e]], self.capacity[e], self.edge_flow(e)))
+            .collect()
+    }
+
+    // sum of positive flow (in, out) for every vertex
+    fn balances(&self) -> Vec<(i32, i32)> {
+        let mut res = vec![(0, 0); self.len()];
+        for e in (0..self.to.len()).step_by(2) {
+            let f = self.edge_flow(e);
+            if f <= 0 { continue; }
+            res[self.to[e^1]].1 += f;
+            res[self.to[e]].0 += f;
+        }
+        res
+    }
+
+    // debug functions
+    #[allow(dead_code)]
+    fn assert_only_one_saturated(&self, source: i32, sink: i32) {
+        for (v, (sum_in, _)) in self.balances().iter().enumerate() {
+            if self.names[v] == sink || self.names[v] == source { continue; }
+            assert!(*sum_in <= 1, "v={} in={}", self.names[v], sum_in);
+        }
+    }
+
+    #[allow(dead_code)]
+    fn assert_incoming_equals_outgoing(&self, source: i32, sink: i32) {
+        for (v, (sum_in, sum_out)) in self.balances().iter().enumerate() {
+            if self.names[v] == sink || self.names[v] == source { continue; }
+            assert!(sum_in == sum_out, "in!=out, v={}, in={}, out={}", self.names[v], sum_in, sum_out);
+        }
+    }
+}
+
+// https://en.wikipedia.org/wiki/Dinic%27s_algorithm
+#[derive(Clone)]
+pub struct Dinic {
+    flow: i32,
+    source: i32,
+    sink: i32,
+    net: Network,
+    level: Vec<i32>,
+    next_edge: Vec<usize>,
+}
+
+impl Dinic {
+    fn bfs(&mut self, s: usize, t: usize) -> bool {
+        self.level = vec![-1; self.net.len()];
+        self.level[s] = 0;
+        let mut q = VecDeque::new();
+        q.push_back(s);
+        while let Some(v) = q.pop_front() {
+            for &e in self.net.adj[v].iter() {
+                let u = self.net.to[e];
+                if self.net.residual[e] > 0 && self.level[u] == -1 {
+                    self.level[u] = self.level[v]+1;
+                    q.push_back(u);
+                }
+            }
+        }
+        self.level[t] != -1
+    }
+
+    fn dfs(&mut self, v: usize, t: usize, pushed: i32) -> i32 {
+        if v == t { return pushed; }
+        while self.next_edge[v] < self.net.adj[v].len() {
+            let e = self.net.adj[v][self.next_edge[v]];
+            let u = self.net.to[e];
+            if self.net.residual[e] > 0 && self.level[u] == self.level[v]+1 {
+                let got = self.dfs(u, t, std::cmp::min(pushed, self.net.residual[e]));
+                if got > 0 {
+                    self.net.push(e, got);
+                    return got;
+                }
+            }
+            self.next_edge[v] += 1;
+        }
+        0
+    }
+}
+
+impl MaxFlow for Dinic {
+    fn new() -> Self {
+        Dinic {
+            flow: -1,
+            source: -1,
+            sink: -1,
+            net: Network::new(),
+            level: Vec::new(),
+            next_edge: Vec::new(),
+        }
+    }
+
+    fn reset(&mut self) {
+        *self = Dinic::new();
+    }
+
+    fn add_edge(&mut self, from: i32, to: i32, capacity: i32) {
+        self.net.add_edge(from, to, capacity);
+    }
+
+    // continues from the current flow, so edges can be added between calls
+    fn solve(&mut self) {
+        if self.flow == -1 { self.flow = 0; }
+        let (s, t) = match (self.net.ids.get(&self.source), self.net.ids.get(&self.sink)) {
+            (Some(&s), Some(&t)) => (s, t),
+            _ => return,
+        };
+        while self.bfs(s, t) {
+            self.next_edge = vec![0; self.net.len()];
+            loop {
+                let pushed = self.dfs(s, t, i32::MAX);
+                if pushed == 0 { break; }
+                self.flow += pushed;
+            }
+        }
+
+        #[cfg(feature = "flow-checks")]
+        if let Err(e) = self.verify(self.flow) { panic!("invalid flow: {}", e); }
+    }
+
+    fn get_flow(&mut self) -> i32 {
+        if self.flow == -1 { self.solve(); }
+        self.flow
+    }
+
+    fn set_source(&mut self, source: i32) { self.source = source; }
+    fn set_sink(&mut self, sink: i32) { self.sink = sink; }
+    fn get_source(&self) -> i32 { self.source }
+    fn get_sink(&self) -> i32 { self.sink }
+
+    fn get_saturated_edge(&self, vertex: i32) -> Option<(i32, i32)> {
+        self.net.saturated_edge(vertex)
+    }
+
+    fn get_edges(&self) -> Vec<(i32, i32, i32, i32)> {
+        self.net.edges()
+    }
+
+    fn assert_only_one_saturated(&self) {
+        self.net.assert_only_one_saturated(self.source, self.sink);
+    }
+
+    fn assert_incoming_equals_outgoing(&self) {
+        self.net.assert_incoming_equals_outgoing(self.source, self.sink);
+    }
+}
+
+// https://en.wikipedia.org/wiki/Push%E2%80%93relabel_maximum_flow_algorithm
+// highest label selection with gap and global relabelling heuristics
+#[derive(Clone)]
+pub struct PushRelabel {
+    flow: i32,
+    source: i32,
+    sink: i32,
+    net: Network,
+    height: Vec<usize>,
+    excess: Vec<i64>,
+    // number of vertices with given height
+    count: Vec<usize>,
+    // active vertices by height
+    buckets: Vec<Vec<usize>>,
+    // no active vertex is above this height
+    highest: usize,
+    next_edge: Vec<usize>,
+}
+
+impl PushRelabel {
+    fn activate(&mut self, v: usize) {
+        self.buckets[self.height[v]].push(v);
+        self.highest = std::cmp::max(self.highest, self.height[v]);
+    }
+
+    // exact heights: distance to sink in residual network, vertices that
+    // cannot reach the sink get n + distance to source (their excess goes back)
+    fn global_relabel(&mut self, s: usize, t: usize) {
+        let n = self.net.len();
+        self.height = vec![2*n; n];
+        for (root, base) in [(t, 0), (s, n)] {
+            self.height[root] = base;
+            let mut q = VecDeque::new();
+            q.push_back(root);
+            while let Some(v) = q.pop_front() {
+                for &e in self.net.adj[v].iter() {
+                    let u = self.net.to[e];
+                    // u -> v is the paired edge
+                    if self.net.residual[e^1] > 0 && self.height[u] == 2*n {
+                        self.height[u] = self.height[v]+1;
+                        q.push_back(u);
+                    }
+                }
+            }
+        }
+
+        self.count = vec![0; 2*n+1];
+        self.buckets = vec![Vec::new(); 2*n+1];
+        self.highest = 0;
+        for v in 0..n {
+            self.count[self.height[v]] += 1;
+            if v != s && v != t && self.excess[v] > 0 {
+                self.activate(v);
+            }
+        }
+        self.next_edge = vec![0; n];
+    }
+
+    fn push(&mut self, e: usize, v: usize, s: usize, t: usize) {
+        let u = self.net.to[e];
+        let amount = std::cmp::min(self.excess[v], self.net.residual[e] as i64);
+        self.net.push(e, amount as i32);
+        self.excess[v] -= amount;
+        if self.excess[u] == 0 && u != s && u != t {
+            self.activate(u);
+        }
+        self.excess[u] += amount;
+    }
+
+    fn relabel(&mut self, v: usize, s: usize, t: usize) {
+        let n = self.net.len();
+        let old = self.height[v];
+        let mut best = 2*n;
+        for &e in self.net.adj[v].iter() {
+            if self.net.residual[e] > 0 {
+                best = std::cmp::min(best, self.height[self.net.to[e]]+1);
+            }
+        }
+        self.count[old] -= 1;
+        self.height[v] = best;
+        self.count[best] += 1;
+        self.next_edge[v] = 0;
+
+        // gap: nothing at height old -> everything above (below n) is cut off from sink
+        if self.count[old] == 0 && old < n {
+            for u in 0..n {
+                if self.height[u] > old && self.height[u] < n {
+                    self.count[self.height[u]] -= 1;
+                    self.height[u] = n+1;
+                    self.count[n+1] += 1;
+                    self.next_edge[u] = 0;
+                    if u != v && u != s && u != t && self.excess[u] > 0 { self.activate(u); }
+                }
+            }
+        }
+    }
+
+    // pushes all excess from v, returns true if v was relabelled
+    fn discharge(&mut self, v: usize, s: usize, t: usize) -> bool {
+        let mut relabelled = false;
+        while self.excess[v] > 0 {
+            if self.next_edge[v] == self.net.adj[v].len() {
+                self.relabel(v, s, t);
+                relabelled = true;
+                if self.height[v] >= 2*self.net.len() { break; }
+                continue;
+            }
+            let e = self.net.adj[v][self.next_edge[v]];
+            let u = self.net.to[e];
+            if self.net.residual[e] > 0 && self.height[v] == self.height[u]+1 {
+                self.push(e, v, s, t);
+            }
+            else {
+                self.next_edge[v] += 1;
+            }
+        }
+        relabelled
+    }
+}
+
+impl MaxFlow for PushRelabel {
+    fn new() -> Self {
+        PushRelabel {
+            flow: -1,
+            source: -1,
+            sink: -1,
+            net: Network::new(),
+            height: Vec::new(),
+            excess: Vec::new(),
+            count: Vec::new(),
+            buckets: Vec::new(),
+            highest: 0,
+            next_edge: Vec::new(),
+        }
+    }
+
+    fn reset(&mut self) {
+        *self = PushRelabel::new();
+    }
+
+    fn add_edge(&mut self, from: i32, to: i32, capacity: i32) {
+        self.net.add_edge(from, to, capacity);
+    }
+
+    // continues from the current flow, so edges can be added between calls
+    fn solve(&mut self) {
+        if self.flow == -1 { self.flow = 0; }
+        let (s, t) = match (self.net.ids.get(&self.source), self.net.ids.get(&self.sink)) {
+            (Some(&s), Some(&t)) => (s, t),
+            _ => return,
+        };
+        let n = self.net.len();
+
+        // saturate everything leaving the source
+        self.excess = vec![0; n];
+        for idx in 0..self.net.adj[s].len() {
+            let e = self.net.adj[s][idx];
+            let amount = self.net.residual[e];
+            if amount == 0 { continue; }
+            self.net.push(e, amount);
+            self.excess[self.net.to[e]] += amount as i64;
+        }
+
+        self.global_relabel(s, t);
+        let mut relabels = 0;
+        loop {
+            while self.highest > 0 && self.buckets[self.highest].is_empty() { self.highest -= 1; }
+            let v = match self.buckets[self.highest].pop() {
+                Some(v) => v,
+                None => break,
+            };
+            // stale entry, vertex was already discharged or moved
+            if self.excess[v] == 0 || self.height[v] != self.highest { continue; }
+
+            if self.discharge(v, s, t) {
+                relabels += 1;
+                if relabels >= n {
+                    relabels = 0;
+                    self.global_relabel(s, t);
+                }
+            }
+            if self.excess[v] > 0 && self.height[v] < 2*n {
+                self.activate(v);
+            }
+        }
+
+        self.flow = -self.net.outflow(t);
+
+        #[cfg(feature = "flow-checks")]
+        if let Err(e) = self.verify(self.flow) { panic!("invalid flow: {}", e); }
+    }
+
+    fn get_flow(&mut self) -> i32 {
+        if self.flow == -1 { self.solve(); }
+        self.flow
+    }
+
+    fn set_source(&mut self, source: i32) { self.source = source; }
+    fn set_sink(&mut self, sink: i32) { self.sink = sink; }
+    fn get_source(&self) -> i32 { self.source }
+    fn get_sink(&self) -> i32 { self.sink }
+
+    fn get_saturated_edge(&self, vertex: i32) -> Option<(i32, i32)> {
+        self.net.saturated_edge(vertex)
+    }
+
+    fn get_edges(&self) -> Vec<(i32, i32, i32, i32)> {
+        self.net.edges()
+    }
+
+    fn assert_only_one_saturated(&self) {
+        self.net.assert_only_one_saturated(self.source, self.sink);
+    }
+
+    fn assert_incoming_equals_outgoing(&self) {
+        self.net.assert_incoming_equals_outgoing(self.source, self.sink);
+    }
+}
+
+// successive shortest paths with johnson potentials
+// bellman-ford for the initial potentials (costs may be negative), then dijkstra
+// on reduced costs, expects no negative cycles in the residual network
+#[derive(Clone)]
+pub struct SuccessiveShortestPaths {
+    flow: i32,
+    source: i32,
+    sink: i32,
+    net: Network,
+    // cost[e^1] == -cost[e]
+    cost: Vec<i64>,
+    potential: Vec<i64>,
+}
+
+impl SuccessiveShortestPaths {
+    const INF: i64 = i64::MAX/4;
+
+    fn bellman_ford(&mut self, s: usize) {
+        let n = self.net.len();
+        self.potential = vec![Self::INF; n];
+        self.potential[s] = 0;
+        let mut in_queue = vec![false; n];
+        let mut q = VecDeque::new();
+        q.push_back(s);
+        while let Some(v) = q.pop_front() {
+            in_queue[v] = false;
+            for &e in self.net.adj[v].iter() {
+                let u = self.net.to[e];
+                if self.net.residual[e] > 0 && self.potential[v]+self.cost[e] < self.potential[u] {
+                    self.potential[u] = self.potential[v]+self.cost[e];
+                    if !in_queue[u] {
+                        in_queue[u] = true;
+                        q.push_back(u);
+                    }
+                }
+            }
+        }
+    }
+
+    // shortest path tree on reduced costs, returns edge used to enter each vertex
+    fn dijkstra(&self, s: usize) -> (Vec<i64>, Vec<usize>) {
+        let n = self.net.len();
+        let mut dist = vec![Self::INF; n];
+        let mut parent = vec![usize::MAX; n];
+        let mut heap = BinaryHeap::new();
+        dist[s] = 0;
+        heap.push(Reverse((0, s)));
+        while let Some(Reverse((d, v))) = heap.pop() {
+            if d > dist[v] { continue; }
+            for &e in self.net.adj[v].iter() {
+                let u = self.net.to[e];
+                if self.net.residual[e] <= 0 || self.potential[u] == Self::INF { continue; }
+                let nd = d+self.cost[e]+self.potential[v]-self.potential[u];
+                if nd < dist[u] {
+                    dist[u] = nd;
+                    parent[u] = e;
+                    heap.push(Reverse((nd, u)));
+                }
+            }
+        }
+        (dist, parent)
+    }
+}
+
+impl MaxFlow for SuccessiveShortestPaths {
+    fn new() -> Self {
+        SuccessiveShortestPaths {
+            flow: -1,
+            source: -1,
+            sink: -1,
+            net: Network::new(),
+            cost: Vec::new(),
+            potential: Vec::new(),
+        }
+    }
+
+    fn reset(&mut self) {
+        *self = SuccessiveShortestPaths::new();
+    }
+
+    fn add_edge(&mut self, from: i32, to: i32, capacity: i32) {
+        self.add_cost_edge(from, to, capacity, 0);
+    }
+
+    // continues from the current flow, so edges can be added between calls
+    fn solve(&mut self) {
+        if self.flow == -1 { self.flow = 0; }
+        let (s, t) = match (self.net.ids.get(&self.source), self.net.ids.get(&self.sink)) {
+            (Some(&s), Some(&t)) => (s, t),
+            _ => return,
+        };
+        self.bellman_ford(s);
+        loop {
+            let (dist, parent) = self.dijkstra(s);
+            if dist[t] == Self::INF { break; }
+            for (v, d) in dist.iter().enumerate() {
+                if *d < Self::INF { self.potential[v] += d; }
+            }
+
+            let mut pushed = i32::MAX;
+            let mut v = t;
+            while v != s {
+                let e = parent[v];
+                pushed = std::cmp::min(pushed, self.net.residual[e]);
+                v = self.net.to[e^1];
+            }
+            let mut v = t;
+            while v != s {
+                let e = parent[v];
+                self.net.push(e, pushed);
+                v = self.net.to[e^1];
+            }
+            self.flow += pushed;
+        }
+
+        #[cfg(feature = "flow-checks")]
+        if let Err(e) = self.verify(self.flow) { panic!("invalid flow: {}", e); }
+    }
+
+    fn get_flow(&mut self) -> i32 {
+        if self.flow == -1 { self.solve(); }
+        self.flow
+    }
+
+    fn set_source(&mut self, source: i32) { self.source = source; }
+    fn set_sink(&mut self, sink: i32) { self.sink = sink; }
+    fn get_source(&self) -> i32 { self.source }
+    fn get_sink(&self) -> i32 { self.sink }
+
+    fn get_saturated_edge(&self, vertex: i32) -> Option<(i32, i32)> {
+        self.net.saturated_edge(vertex)
+    }
+
+    fn get_edges(&self) -> Vec<(i32, i32, i32, i32)> {
+        self.net.edges()
+    }
+
+    fn assert_only_one_saturated(&self) {
+        self.net.assert_only_one_saturated(self.source, self.sink);
+    }
+
+    fn assert_incoming_equals_outgoing(&self) {
+        self.net.assert_incoming_equals_outgoing(self.source, self.sink);
+    }
+}
+
+impl MinCostFlow for SuccessiveShortestPaths {
+    fn add_cost_edge(&mut self, from: i32, to: i32, capacity: i32, cost: i64) {
+        self.net.add_edge(from, to, capacity);
+        self.cost.push(cost);
+        self.cost.push(-cost);
+    }
+
+    fn get_cost(&mut self) -> i64 {
+        if self.flow == -1 { self.solve(); }
+        (0..self.cost.len()).step_by(2)
+            .map(|e| self.net.edge_flow(e) as i64*self.cost[e])
+            .sum()
+    }
+}
+
+// selects MaxFlow implementation (trait is not object safe)
+#[allow(dead_code)]
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum FlowBackends {
+    FordFulkerson,
+    Dinic,
+    PushRelabel,
+}
+
 #[cfg(test)]
 mod tests {
    use crate::flow::*;
+   use rand::Rng;
 
     #[test]
     fn simple() {
@@ -252,4 +1007,348 @@ mod tests {
         //println!("{:?}", ff.capacities);
         assert_eq!(ff.flow, 10);
     }
+
+    fn ad_exam_edges() -> Vec<(i32, i32, i32)> {
+        vec![(0, 1, 7), (0, 2, 3), (0, 3, 5), (1, 2, 6), (1, 4, 9), (2, 3, 2), (2, 5, 1),
+             (3, 6, 9), (4, 2, 7), (4, 7, 2), (5, 4, 5), (5, 7, 9), (6, 2, 2), (6, 5, 4), (6, 7, 7)]
+    }
+
+    fn flow_of(flow: &mut impl MaxFlow, source: i32, sink: i32, edges: &[(i32, i32, i32)]) -> i32 {
+        flow.reset();
+        flow.set_source(source);
+        flow.set_sink(sink);
+        for &(from, to, capacity) in edges.iter() {
+            flow.add_edge(from, to, capacity);
+        }
+        flow.get_flow()
+    }
+
+    // random graph without parallel and antiparallel edges (FordFulkerson keys edges by pair)
+    fn random_edges(rng: &mut impl Rng, n: i32, p: f64, max_cap: i32) -> Vec<(i32, i32, i32)> {
+        let mut res = Vec::new();
+        for v in 0..n {
+            for u in v+1..n {
+                if !rng.gen_bool(p) { continue; }
+                let cap = rng.gen_range(1..=max_cap);
+                if rng.gen_bool(0.5) { res.push((v, u, cap)); }
+                else { res.push((u, v, cap)); }
+            }
+        }
+        res
+    }
+
+    #[test]
+    fn dinic_small() {
+        let mut dinic = Dinic::new();
+        assert_eq!(flow_of(&mut dinic, 0, 2, &[(0, 1, 3), (1, 2, 2)]), 2);
+        assert_eq!(flow_of(&mut dinic, 0, 3, &[(0, 1, 3), (0, 2, 2), (1, 2, 1), (1, 3, 2), (2, 3, 3)]), 5);
+        assert_eq!(flow_of(&mut dinic, 0, 7, &ad_exam_edges()), 10);
+        dinic.assert_incoming_equals_outgoing();
+        // no edges at the source
+        assert_eq!(flow_of(&mut dinic, 5, 7, &[(0, 7, 1)]), 0);
+    }
+
+    #[test]
+    fn dinic_negative_ids() {
+        let mut dinic = Dinic::new();
+        let edges = vec![(-1, 10, 1), (-1, 20, 1), (20, 30, 1), (30, -3, 1), (10, -3, 1), (-3, -2, 5)];
+        assert_eq!(flow_of(&mut dinic, -1, -2, &edges), 2);
+        assert_eq!(dinic.get_saturated_edge(20), Some((20, 30)));
+        assert_eq!(dinic.get_saturated_edge(-2), None);
+        dinic.assert_incoming_equals_outgoing();
+    }
+
+    #[test]
+    fn dinic_incremental() {
+        let mut dinic = Dinic::new();
+        assert_eq!(flow_of(&mut dinic, 0, 3, &[(0, 1, 2), (1, 3, 1)]), 1);
+        dinic.add_edge(1, 2, 1);
+        dinic.add_edge(2, 3, 4);
+        dinic.solve();
+        assert_eq!(dinic.get_flow(), 2);
+    }
+
+    #[test]
+    fn ford_fulkerson_incremental() {
+        let mut ff = FordFulkerson::new();
+        assert_eq!(flow_of(&mut ff, 0, 3, &[(0, 1, 2), (1, 3, 1)]), 1);
+        ff.add_edge(1, 2, 1);
+        ff.add_edge(2, 3, 4);
+        ff.solve();
+        assert_eq!(ff.get_flow(), 2);
+    }
+
+    #[test]
+    fn dinic_against_ford_fulkerson() {
+        let mut rng = rand::thread_rng();
+        let mut ff = FordFulkerson::new();
+        let mut dinic = Dinic::new();
+        for _ in 0..200 {
+            let n = rng.gen_range(2..15);
+            let edges = random_edges(&mut rng, n, 0.4, 10);
+            let exp = flow_of(&mut ff, 0, n-1, &edges);
+            assert_eq!(flow_of(&mut dinic, 0, n-1, &edges), exp, "{:?}", edges);
+            dinic.assert_incoming_equals_outgoing();
+        }
+    }
+
+    #[test]
+    fn push_relabel_small() {
+        let mut pr = PushRelabel::new();
+        assert_eq!(flow_of(&mut pr, 0, 2, &[(0, 1, 3), (1, 2, 2)]), 2);
+        pr.assert_incoming_equals_outgoing();
+        assert_eq!(flow_of(&mut pr, 0, 3, &[(0, 1, 3), (0, 2, 2), (1, 2, 1), (1, 3, 2), (2, 3, 3)]), 5);
+        assert_eq!(flow_of(&mut pr, 0, 7, &ad_exam_edges()), 10);
+        pr.assert_incoming_equals_outgoing();
+        assert_eq!(flow_of(&mut pr, 5, 7, &[(0, 7, 1)]), 0);
+    }
+
+    #[test]
+    fn push_relabel_incremental() {
+        let mut pr = PushRelabel::new();
+        assert_eq!(flow_of(&mut pr, 0, 3, &[(0, 1, 2), (1, 3, 1)]), 1);
+        pr.add_edge(1, 2, 1);
+        pr.add_edge(2, 3, 4);
+        pr.solve();
+        assert_eq!(pr.get_flow(), 2);
+        pr.assert_incoming_equals_outgoing();
+    }
+
+    #[test]
+    fn push_relabel_against_ford_fulkerson() {
+        let mut rng = rand::thread_rng();
+        let mut ff = FordFulkerson::new();
+        let mut pr = PushRelabel::new();
+        for _ in 0..300 {
+            let n = rng.gen_range(2..20);
+            let edges = random_edges(&mut rng, n, 0.3, 10);
+            let exp = flow_of(&mut ff, 0, n-1, &edges);
+            assert_eq!(flow_of(&mut pr, 0, n-1, &edges), exp, "{:?}", edges);
+            pr.assert_incoming_equals_outgoing();
+        }
+    }
+
+    #[test]
+    fn backends_on_large_layered() {
+        // unit capacity layered network, similar to time expanded graphs
+        let mut rng = rand::thread_rng();
+        let layers = 60;
+        let width = 40;
+        let mut edges = Vec::new();
+        for v in 0..width {
+            edges.push((-1, v, 1));
+            edges.push(((layers-1)*width+v, -2, 1));
+        }
+        for layer in 0..layers-1 {
+            for v in 0..width {
+                for _ in 0..3 {
+                    let u = rng.gen_range(0..width);
+                    edges.push((layer*width+v, (layer+1)*width+u, 1));
+                }
+            }
+        }
+        edges.sort();
+        edges.dedup_by_key(|e| (e.0, e.1));
+
+        let exp = flow_of(&mut Dinic::new(), -1, -2, &edges);
+        assert_eq!(flow_of(&mut PushRelabel::new(), -1, -2, &edges), exp);
+        assert_eq!(flow_of(&mut FordFulkerson::new(), -1, -2, &edges), exp);
+    }
+
+    // min cost of a perfect assignment by trying every permutation
+    fn brute_assignment(cost: &[Vec<i64>], idx: usize, used: &mut Vec<bool>) -> i64 {
+        if idx == cost.len() { return 0; }
+        let mut best = i64::MAX;
+        for j in 0..used.len() {
+            if used[j] { continue; }
+            used[j] = true;
+            best = std::cmp::min(best, cost[idx][j]+brute_assignment(cost, idx+1, used));
+            used[j] = false;
+        }
+        best
+    }
+
+    #[test]
+    fn min_cost_small() {
+        // two routes to the sink, the cheap one only fits a single unit
+        let mut flow = SuccessiveShortestPaths::new();
+        flow.set_source(0);
+        flow.set_sink(3);
+        flow.add_cost_edge(0, 1, 2, 0);
+        flow.add_cost_edge(1, 3, 1, 1);
+        flow.add_cost_edge(1, 2, 2, 2);
+        flow.add_cost_edge(2, 3, 2, 2);
+        flow.solve();
+        assert_eq!(flow.get_flow(), 2);
+        assert_eq!(flow.get_cost(), 5);
+        flow.assert_incoming_equals_outgoing();
+    }
+
+    #[test]
+    fn min_cost_negative_costs() {
+        let mut flow = SuccessiveShortestPaths::new();
+        flow.set_source(-1);
+        flow.set_sink(-2);
+        flow.add_cost_edge(-1, 0, 1, 0);
+        flow.add_cost_edge(-1, 1, 1, 0);
+        flow.add_cost_edge(0, -2, 1, -5);
+        flow.add_cost_edge(1, 0, 1, -3);
+        flow.add_cost_edge(1, -2, 1, 2);
+        assert_eq!(flow.get_flow(), 2);
+        assert_eq!(flow.get_cost(), -3);
+    }
+
+    #[test]
+    fn min_cost_against_dinic() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..50 {
+            let n = rng.gen_range(2..30);
+            let edges = random_edges(&mut rng, n, 0.2, 8);
+            let exp = flow_of(&mut Dinic::new(), 0, n-1, &edges);
+            assert_eq!(flow_of(&mut SuccessiveShortestPaths::new(), 0, n-1, &edges), exp);
+        }
+    }
+
+    #[test]
+    fn min_cost_against_brute_force_assignment() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..100 {
+            let n = rng.gen_range(1..6);
+            let cost = (0..n).map(|_| (0..n).map(|_| rng.gen_range(-10..40)).collect())
+                .collect::<Vec<Vec<i64>>>();
+
+            let mut flow = SuccessiveShortestPaths::new();
+            flow.set_source(-1);
+            flow.set_sink(-2);
+            for (i, row) in cost.iter().enumerate() {
+                flow.add_edge(-1, i as i32, 1);
+                flow.add_edge((n+i) as i32, -2, 1);
+                for (j, c) in row.iter().enumerate() {
+                    flow.add_cost_edge(i as i32, (n+j) as i32, 1, *c);
+                }
+            }
+            assert_eq!(flow.get_flow(), n as i32);
+            assert_eq!(flow.get_cost(), brute_assignment(&cost, 0, &mut vec![false; n]), "{:?}", cost);
+        }
+    }
+
+    // paths have to follow edges, respect their flow and add up to the max flow
+    fn check_decomposition(flow: &mut impl MaxFlow, edges: &[(i32, i32, i32)]) {
+        let got = flow.get_flow();
+        let paths = flow.decompose();
+        let mut used: HashMap<(i32, i32), i32> = HashMap::new();
+        for (path, amount) in paths.iter() {
+            assert!(*amount > 0);
+            assert_eq!(path[0], flow.get_source());
+            assert_eq!(*path.last().unwrap(), flow.get_sink());
+            for x in path.windows(2) {
+                *used.entry((x[0], x[1])).or_insert(0) += amount;
+            }
+        }
+        for (key, amount) in used.iter() {
+            let cap = edges.iter().filter(|e| (e.0, e.1) == *key).map(|e| e.2).sum::<i32>();
+            assert!(*amount <= cap, "{:?} {} {}", key, amount, cap);
+        }
+        assert_eq!(paths.iter().map(|x| x.1).sum::<i32>(), got);
+    }
+
+    #[test]
+    fn decompose_small() {
+        let mut dinic = Dinic::new();
+        let edges = [(0, 1, 2), (0, 2, 1), (1, 3, 1), (1, 2, 1), (2, 3, 2)];
+        assert_eq!(flow_of(&mut dinic, 0, 3, &edges), 3);
+        let mut paths = dinic.decompose();
+        paths.sort();
+        assert_eq!(paths, vec![(vec![0, 1, 2, 3], 1), (vec![0, 1, 3], 1), (vec![0, 2, 3], 1)]);
+    }
+
+    #[test]
+    fn decompose_random() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..50 {
+            let n = rng.gen_range(2..30);
+            let edges = random_edges(&mut rng, n, 0.2, 5);
+            let mut ff = FordFulkerson::new();
+            flow_of(&mut ff, 0, n-1, &edges);
+            check_decomposition(&mut ff, &edges);
+            let mut dinic = Dinic::new();
+            flow_of(&mut dinic, 0, n-1, &edges);
+            check_decomposition(&mut dinic, &edges);
+            let mut pr = PushRelabel::new();
+            flow_of(&mut pr, 0, n-1, &edges);
+            check_decomposition(&mut pr, &edges);
+            let mut ssp = SuccessiveShortestPaths::new();
+            flow_of(&mut ssp, 0, n-1, &edges);
+            check_decomposition(&mut ssp, &edges);
+        }
+    }
+
+    #[test]
+    fn min_cut_small() {
+        let mut dinic = Dinic::new();
+        let edges = [(0, 1, 3), (0, 2, 2), (1, 2, 5), (1, 3, 1), (2, 3, 2)];
+        assert_eq!(flow_of(&mut dinic, 0, 3, &edges), 3);
+        let mut cut = dinic.min_cut();
+        cut.sort();
+        assert_eq!(cut, vec![(1, 3), (2, 3)]);
+    }
+
+    #[test]
+    fn min_cut_random() {
+        // capacity of the cut has to equal the max flow for every backend
+        let mut rng = rand::thread_rng();
+        let capacity = |cut: Vec<(i32, i32)>, edges: &[(i32, i32, i32)]| cut.iter()
+            .map(|c| edges.iter().filter(|e| (e.0, e.1) == *c).map(|e| e.2).sum::<i32>())
+            .sum::<i32>();
+        for _ in 0..50 {
+            let n = rng.gen_range(2..30);
+            let edges = random_edges(&mut rng, n, 0.2, 5);
+            let mut ff = FordFulkerson::new();
+            let exp = flow_of(&mut ff, 0, n-1, &edges);
+            assert_eq!(capacity(ff.min_cut(), &edges), exp);
+            let mut dinic = Dinic::new();
+            flow_of(&mut dinic, 0, n-1, &edges);
+            assert_eq!(capacity(dinic.min_cut(), &edges), exp);
+            let mut pr = PushRelabel::new();
+            flow_of(&mut pr, 0, n-1, &edges);
+            assert_eq!(capacity(pr.min_cut(), &edges), exp);
+            let mut ssp = SuccessiveShortestPaths::new();
+            flow_of(&mut ssp, 0, n-1, &edges);
+            assert_eq!(capacity(ssp.min_cut(), &edges), exp);
+        }
+    }
+
+    #[test]
+    fn verify_backends() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..50 {
+            let n = rng.gen_range(2..30);
+            let edges = random_edges(&mut rng, n, 0.2, 5);
+            let mut ff = FordFulkerson::new();
+            let got = flow_of(&mut ff, 0, n-1, &edges);
+            assert_eq!(ff.verify(got), Ok(()));
+            let mut dinic = Dinic::new();
+            flow_of(&mut dinic, 0, n-1, &edges);
+            assert_eq!(dinic.verify(got), Ok(()));
+            let mut pr = PushRelabel::new();
+            flow_of(&mut pr, 0, n-1, &edges);
+            assert_eq!(pr.verify(got), Ok(()));
+            let mut ssp = SuccessiveShortestPaths::new();
+            flow_of(&mut ssp, 0, n-1, &edges);
+            assert_eq!(ssp.verify(got), Ok(()));
+        }
+    }
+
+    #[test]
+    fn verify_detects_errors() {
+        let mut dinic = Dinic::new();
+        assert_eq!(flow_of(&mut dinic, 0, 3, &[(0, 1, 2), (1, 3, 1)]), 1);
+        assert!(dinic.verify(2).is_err());
+        // new augmenting path, flow is no longer maximum
+        dinic.add_edge(1, 2, 1);
+        dinic.add_edge(2, 3, 4);
+        assert!(dinic.verify(1).is_err());
+        dinic.solve();
+        assert_eq!(dinic.verify(2), Ok(()));
+    }
 }
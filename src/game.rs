@@ -0,0 +1,355 @@
+use std::collections::HashMap;
+use crate::map::*;
+
+// turns until capture of states the target can escape from forever
+pub const ESCAPES: u32 = u32::MAX;
+// both value tables and the counters take 9 bytes per state, plus the states of one turn
+const MAX_STATES: usize = 1 << 24;
+
+// exact pursuit game of a few agents against a single target on small maps, same
+// rules as Runner: the target moves first (and rests when its timer is 0), then every
+// agent moves, the target is caught when an agent stands on it (any agent catches it)
+// values are found by retrograde analysis backwards from the captures, every state is
+// solved once and only its predecessors are looked at
+pub struct GameSolver {
+    pub agents: usize,
+    pub d_time: i32,
+    cells: Vec<Point>,
+    cell_idx: HashMap<Point, usize>,
+    // cells reachable in one turn, staying first
+    moves: Vec<Vec<usize>>,
+    // cells one turn can come from
+    back: Vec<Vec<usize>>,
+    // turns until capture with the target to move
+    value: Vec<u32>,
+}
+
+impl GameSolver {
+    pub fn new(map: &Map, agents: usize, d_time: i32) -> Result<Self, String> {
+        if d_time < 0 {
+            return Err(format!("negative d_time {}", d_time));
+        }
+        let mut cells = Vec::new();
+        for x in 0..map.width {
+            for y in 0..map.height {
+                if map.valid_point(&Point{x, y}) { cells.push(Point{x, y}); }
+            }
+        }
+        let states = (cells.len() as f64).powi(agents as i32 + 1) * (d_time as f64 + 1.0);
+        if states > MAX_STATES as f64 {
+            return Err(format!("{} agents, {} cells and d_time {} give too many states ({})",
+                               agents, cells.len(), d_time, states));
+        }
+
+        let cell_idx = cells.iter()
+            .enumerate()
+            .map(|(idx, p)| (*p, idx))
+            .collect::<HashMap<_, _>>();
+        let moves = cells.iter().map(|p| {
+            [Direction::None, Direction::North, Direction::East, Direction::South, Direction::West].iter()
+                .filter(|dir| map.valid_direction(*p, **dir))
+                .map(|dir| go_direction(*p, *dir))
+                .filter_map(|q| cell_idx.get(&q).copied())
+                .collect()
+        }).collect::<Vec<Vec<usize>>>();
+        let mut back = vec![Vec::new(); cells.len()];
+        for (from, next) in moves.iter().enumerate() {
+            for to in next.iter() { back[*to].push(from); }
+        }
+
+        let mut res = GameSolver {
+            agents,
+            d_time,
+            cells,
+            cell_idx,
+            moves,
+            back,
+            value: vec![ESCAPES; states as usize],
+        };
+        res.solve();
+        Ok(res)
+    }
+
+    // states after the target moved (agents to move) take the value of their first solved
+    // successor, states with the target to move are solved once every target move is, values
+    // are solved in increasing order, so those are the smallest and the largest
+    fn solve(&mut self) {
+        let states = self.value.len();
+        // turns until capture after the target moved, 0 when an agent steps on it now
+        let mut after = vec![ESCAPES; states];
+        // target moves not solved yet
+        let mut remaining = (0..states).map(|state| {
+            let (_, target, timer) = self.decode(state);
+            self.target_moves(target, timer).len() as u8
+        }).collect::<Vec<_>>();
+
+        // states solved with value level, after the target moved and with the target to move
+        let mut after_level = Vec::new();
+        for (state, value) in after.iter_mut().enumerate() {
+            let (agents, target, _) = self.decode(state);
+            if agents.iter().any(|agent| self.moves[*agent].contains(&target)) {
+                *value = 0;
+                after_level.push(state);
+            }
+        }
+        let mut before_level = Vec::new();
+        let mut turns = 0;
+        while !after_level.is_empty() || !before_level.is_empty() {
+            // agent moves without a capture leading to states solved with value turns
+            for state in before_level.drain(..) {
+                let (agents, target, timer) = self.decode(state);
+                if agents.contains(&target) { continue; }
+                let mut choice = vec![0; agents.len()];
+                loop {
+                    let prev = agents.iter()
+                        .zip(choice.iter())
+                        .map(|(agent, c)| self.back[*agent][*c])
+                        .collect::<Vec<_>>();
+                    let prev_state = self.encode(&prev, target, timer);
+                    if after[prev_state] == ESCAPES {
+                        after[prev_state] = turns;
+                        after_level.push(prev_state);
+                    }
+
+                    // next joint move
+                    let mut idx = 0;
+                    while idx < agents.len() && choice[idx]+1 == self.back[agents[idx]].len() {
+                        choice[idx] = 0;
+                        idx += 1;
+                    }
+                    if idx == agents.len() { break; }
+                    choice[idx] += 1;
+                }
+            }
+
+            // target moves leading to states solved with value turns
+            let mut next_level = Vec::new();
+            for state in after_level.drain(..) {
+                let (agents, target, timer) = self.decode(state);
+                for (prev, prev_timer) in self.target_predecessors(target, timer) {
+                    let prev_state = self.encode(&agents, prev, prev_timer);
+                    remaining[prev_state] -= 1;
+                    if remaining[prev_state] == 0 {
+                        self.value[prev_state] = turns+1;
+                        next_level.push(prev_state);
+                    }
+                }
+            }
+            before_level = next_level;
+            turns += 1;
+        }
+    }
+
+    // (cell, timer) of the target before every move that ends at target with timer
+    fn target_predecessors(&self, target: usize, timer: i32) -> Vec<(usize, i32)> {
+        if timer == self.d_time {
+            // rested, with any timer
+            return (0..=self.d_time).map(|prev_timer| (target, prev_timer)).collect();
+        }
+        self.back[target].iter()
+            .filter(|prev| **prev != target)
+            .map(|prev| (*prev, timer+1))
+            .collect()
+    }
+
+    fn encode(&self, agents: &[usize], target: usize, timer: i32) -> usize {
+        let mut res = 0;
+        for agent in agents.iter().rev() {
+            res = res*self.cells.len() + agent;
+        }
+        (res*self.cells.len() + target)*(self.d_time as usize + 1) + timer as usize
+    }
+
+    fn decode(&self, mut state: usize) -> (Vec<usize>, usize, i32) {
+        let timer = (state % (self.d_time as usize + 1)) as i32;
+        state /= self.d_time as usize + 1;
+        let target = state % self.cells.len();
+        state /= self.cells.len();
+        let mut agents = Vec::new();
+        for _ in 0..self.agents {
+            agents.push(state % self.cells.len());
+            state /= self.cells.len();
+        }
+        (agents, target, timer)
+    }
+
+    // (cell, timer) after every move allowed to the target
+    fn target_moves(&self, target: usize, timer: i32) -> Vec<(usize, i32)> {
+        if timer == 0 {
+            return vec![(target, self.d_time)];
+        }
+        self.moves[target].iter()
+            .map(|next| (*next, if *next == target { self.d_time } else { timer-1 }))
+            .collect()
+    }
+
+    // best cells for the agents once the target moved, with the turns left after this one
+    // (0 when caught now), the first best joint move wins ties
+    fn answer(&self, agents: &[usize], target: usize, timer: i32) -> (Vec<usize>, u32) {
+        let mut best: Option<(Vec<usize>, u32)> = None;
+        let mut choice = vec![0; agents.len()];
+        loop {
+            let next = agents.iter()
+                .zip(choice.iter())
+                .map(|(agent, c)| self.moves[*agent][*c])
+                .collect::<Vec<_>>();
+            let now = if next.contains(&target) { 0 } else { self.value[self.encode(&next, target, timer)] };
+            if best.as_ref().is_none_or(|x| now < x.1) {
+                best = Some((next, now));
+                if now == 0 { break; }
+            }
+
+            // next joint move
+            let mut idx = 0;
+            while idx < agents.len() && choice[idx]+1 == self.moves[agents[idx]].len() {
+                choice[idx] = 0;
+                idx += 1;
+            }
+            if idx == agents.len() { break; }
+            choice[idx] += 1;
+        }
+        best.unwrap()
+    }
+
+    fn cells_of(&self, agents: &[Point]) -> Vec<usize> {
+        assert_eq!(agents.len(), self.agents);
+        agents.iter().map(|p| self.cell_idx[p]).collect()
+    }
+
+    // turns until capture under optimal play with the target to move, None if it escapes
+    #[allow(dead_code)]
+    pub fn value(&self, agents: &[Point], target: Point, timer: i32) -> Option<u32> {
+        let value = self.value[self.encode(&self.cells_of(agents), self.cell_idx[&target], timer)];
+        if value == ESCAPES { None } else { Some(value) }
+    }
+
+    // optimal agent directions after the target moved to target (timer already updated)
+    pub fn agent_move(&self, map: &Map, agents: &[Point], target: Point, timer: i32) -> Vec<Direction> {
+        let (next, _) = self.answer(&self.cells_of(agents), self.cell_idx[&target], timer);
+        agents.iter()
+            .zip(next.iter())
+            .map(|(p, q)| map.neighbor(p, &self.cells[*q]))
+            .collect()
+    }
+
+    // optimal target direction, latest capture (the first best move wins ties)
+    #[allow(dead_code)]
+    pub fn target_move(&self, map: &Map, agents: &[Point], target: Point, timer: i32) -> Direction {
+        let cells = self.cells_of(agents);
+        let mut best = None;
+        for (next, timer) in self.target_moves(self.cell_idx[&target], timer) {
+            let now = self.answer(&cells, next, timer).1;
+            if best.is_none_or(|(_, x)| now > x) {
+                best = Some((next, now));
+            }
+        }
+        let (next, _) = best.unwrap();
+        map.neighbor(&target, &self.cells[next])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+    use rand::Rng;
+    use crate::game::*;
+    use crate::agent_strategies::*;
+    use crate::target_strategies::*;
+    use crate::runner::Runner;
+
+    fn play(map: &Map, agents: &[Point], target: Point, d_time: i32, agent_strat: Box<dyn AgentStrategy>,
+            mut target_strat: Box<dyn TargetStrategy>) -> i32 {
+        let mut runner = Runner {
+            map,
+            agents: agents_from(&agents.to_vec()),
+            targets: targets_from(&vec![target], d_time),
+            d_time,
+            observation: None,
+        };
+        runner.run(agent_strat, &mut target_strat, false, false, false, false, "", 1000)
+    }
+
+    // plain minimax, can the agents catch the target within turns
+    fn caught_within(map: &Map, agents: &[Point], target: Point, timer: i32, d_time: i32, turns: u32) -> bool {
+        if turns == 0 { return false; }
+        let dirs = [Direction::None, Direction::North, Direction::East, Direction::South, Direction::West];
+        let around = |p: Point| dirs.iter()
+            .map(|dir| go_direction(p, *dir))
+            .filter(|q| map.valid_point(q))
+            .collect::<Vec<_>>();
+        around(target).into_iter()
+            .filter(|next| timer > 0 || *next == target)
+            .all(|next| {
+                let timer = if next == target { d_time } else { timer-1 };
+                // every joint agent move
+                let mut joint = vec![Vec::new()];
+                for agent in agents.iter() {
+                    joint = joint.iter()
+                        .flat_map(|prev| around(*agent).into_iter().map(move |q| [prev.clone(), vec![q]].concat()))
+                        .collect();
+                }
+                joint.iter().any(|next_agents| next_agents.contains(&next) ||
+                                 caught_within(map, next_agents, next, timer, d_time, turns-1))
+            })
+    }
+
+    #[test]
+    fn values_match_minimax() {
+        let mut rng = rand::thread_rng();
+        for (map_name, agents, d_time) in [("example.map", 1, 2), ("example.map", 2, 3), ("simple.map", 1, 1)] {
+            let map = Map::new(&format!("resources/maps/{}", map_name));
+            let solver = GameSolver::new(&map, agents, d_time).unwrap();
+            for _ in 0..20 {
+                let cell = |rng: &mut rand::rngs::ThreadRng| solver.cells[rng.gen_range(0..solver.cells.len())];
+                let agent_points = (0..agents).map(|_| cell(&mut rng)).collect::<Vec<_>>();
+                let target = cell(&mut rng);
+                let timer = rng.gen_range(0..=d_time);
+                let value = solver.value(&agent_points, target, timer);
+                for turns in 1..=3 {
+                    assert_eq!(value.is_some_and(|x| x <= turns),
+                               caught_within(&map, &agent_points, target, timer, d_time, turns),
+                               "{} {:?} {:?} {} {}", map_name, agent_points, target, timer, turns);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn small_values() {
+        let map = Map::new("resources/maps/example.map");
+        let solver = GameSolver::new(&map, 1, 2).unwrap();
+        // target has to rest next to the agent
+        assert_eq!(solver.value(&[Point{x: 1, y: 1}], Point{x: 2, y: 1}, 0), Some(1));
+        // target runs around the ring until it has to rest
+        assert_eq!(solver.value(&[Point{x: 1, y: 1}], Point{x: 2, y: 1}, 2), Some(3));
+        let two = GameSolver::new(&map, 2, 2).unwrap();
+        assert_eq!(two.value(&[Point{x: 1, y: 1}, Point{x: 3, y: 3}], Point{x: 2, y: 1}, 2), Some(2));
+
+        assert!(GameSolver::new(&map, 2, -1).is_err());
+        assert!(GameSolver::new(&map, 2, i32::MAX).is_err());
+    }
+
+    #[test]
+    fn optimal_strategies_realise_value() {
+        let mut rng = rand::thread_rng();
+        let map = Map::new("resources/maps/box.map");
+        let d_time = 2;
+        let solver = Rc::new(GameSolver::new(&map, 1, d_time).unwrap());
+        for _ in 0..10 {
+            let agent = solver.cells[rng.gen_range(0..solver.cells.len())];
+            let target = solver.cells[rng.gen_range(0..solver.cells.len())];
+            let value = solver.value(&[agent], target, d_time).unwrap() as i32;
+
+            let optimal = play(&map, &[agent], target, d_time, Box::new(OptimalAgents::new(solver.clone())),
+                               Box::new(OptimalTarget::new(solver.clone())));
+            assert_eq!(optimal, value);
+            // heuristics measured against ground truth
+            let greedy = play(&map, &[agent], target, d_time, Box::new(MakeSpanHopcroft::new()),
+                              Box::new(OptimalTarget::new(solver.clone())));
+            assert!(greedy >= value);
+            let evader = play(&map, &[agent], target, d_time, Box::new(OptimalAgents::new(solver.clone())),
+                              Box::new(Evader::new(2, d_time)));
+            assert!(evader <= value);
+        }
+    }
+}
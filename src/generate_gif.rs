@@ -2,6 +2,7 @@ use std::fs::File;
 use std::io;
 use gif::{Frame, Encoder, Repeat};
 use std::borrow::Cow;
+use std::collections::HashSet;
 
 use crate::map::*;
 
@@ -10,7 +11,8 @@ use crate::map::*;
 // 2 -> agent (blue)
 // 3 -> target (red)
 // 4 -> agent and target (purple) (anomaly, shouldnt happened)
-pub fn generate_frame(map: &Map, agents: &Vec<Agent>, targets: &Vec<Target>) -> Vec<u8> {
+// 5 -> empty and seen by the agents (light yellow)
+pub fn generate_frame(map: &Map, agents: &Vec<Agent>, targets: &Vec<Target>, seen: &HashSet<Point>) -> Vec<u8> {
     let mut frame = vec![1; map.height*map.width];
     for y in (0..map.height).rev() {
         for x in 0..map.width {
@@ -28,6 +30,9 @@ pub fn generate_frame(map: &Map, agents: &Vec<Agent>, targets: &Vec<Target>) ->
             else if tr {
                 c = 3;
             }
+            else if seen.contains(&Point{x, y}) {
+                c = 5;
+            }
             else if map.valid_point(&Point{x, y}){
                 c = 0;
             }
@@ -46,6 +51,7 @@ pub fn generate_gif(frames: &Vec<Vec<u8>>, map: &Map, file_path: &str) -> Result
             0x00, 0x00, 0xFF, // 2 -> blue
             0xFF, 0x00, 0x00, // 3 -> red
             0xFF, 0x00, 0xFF, // 4 -> purple
+            0xFF, 0xFF, 0xC0, // 5 -> light yellow
         ];
 
     let mut file = match File::create(file_path) {
@@ -16,6 +16,14 @@ pub struct HopcroftKarp {
     pub dist: Vec<usize>,
     pub matching: Vec<i32>,
     pub NIL: usize,
+    // vertex is already in setu or setv
+    pub registered: Vec<bool>,
+}
+
+fn registered(n: usize, setu: &[usize], setv: &[usize]) -> Vec<bool> {
+    let mut res = vec![false; n];
+    for &x in setu.iter().chain(setv.iter()) { res[x] = true; }
+    res
 }
 
 impl HopcroftKarp {
@@ -69,6 +77,7 @@ impl Matcher for HopcroftKarp {
         let nil = graph.len();
         let n = graph.len()+1;
         HopcroftKarp {
+            registered: registered(n, &setu_in, &setv_in),
             g: graph,
             setu: setu_in,
             setv: setv_in,
@@ -90,6 +99,7 @@ impl Matcher for HopcroftKarp {
             dist: Vec::new(),
             matching: Vec::new(),
             NIL: 0,
+            registered: Vec::new(),
         }
     }
 
@@ -97,6 +107,7 @@ impl Matcher for HopcroftKarp {
         let nil = graph.len();
         let n = graph.len()+1;
         self.g = graph;
+        self.registered = registered(n, &setu_in, &setv_in);
         self.setu = setu_in;
         self.setv = setv_in;
         self.pairu = vec![nil; n];
@@ -113,16 +124,7 @@ impl Matcher for HopcroftKarp {
         for &v in self.setv.iter() {
             self.pairv[v] = self.NIL;
         }
-        let mut matching = 0;
-        while self.bfs() {
-            for idx in 0..self.setu.len() {
-                let u = self.setu[idx];
-                if self.pairu[u] == self.NIL && self.dfs(u) {
-                    matching += 1;
-                }
-            }
-        }
-        matching
+        self.augment()
     }
 
     fn get_matching(&mut self) -> &Vec<i32> {
@@ -144,6 +146,88 @@ impl Matcher for HopcroftKarp {
         }
         &self.matching
     }
+
+    fn add_edge(&mut self, u: usize, v: usize) {
+        let n = std::cmp::max(u, v)+1;
+        if n > self.g.len() {
+            let old_nil = self.NIL;
+            // NIL is always the last vertex, so it moves with the size of the graph
+            self.g.resize(n, Vec::new());
+            self.NIL = n;
+            for pair in self.pairu.iter_mut().chain(self.pairv.iter_mut()) {
+                if *pair == old_nil { *pair = n; }
+            }
+            self.pairu.resize(n+1, n);
+            self.pairv.resize(n+1, n);
+            self.dist.resize(n+1, INF);
+            self.matching.resize(n+1, -1);
+            self.registered.resize(n+1, false);
+        }
+        // vertices get their side on first use
+        if !self.registered[u] {
+            self.registered[u] = true;
+            self.setu.push(u);
+        }
+        if !self.registered[v] {
+            self.registered[v] = true;
+            self.setv.push(v);
+        }
+        self.g[u].push(v);
+        self.g[v].push(u);
+    }
+
+    fn remove_edge(&mut self, u: usize, v: usize) {
+        self.g[u].retain(|x| *x != v);
+        self.g[v].retain(|x| *x != u);
+        if self.pairu[u] == v {
+            self.pairu[u] = self.NIL;
+            self.pairv[v] = self.NIL;
+        }
+    }
+
+    fn remove_vertex(&mut self, v: usize) {
+        for idx in 0..self.g[v].len() {
+            let u = self.g[v][idx];
+            self.g[u].retain(|x| *x != v);
+        }
+        self.g[v].clear();
+        if self.pairu[v] != self.NIL {
+            let u = self.pairu[v];
+            self.pairv[u] = self.NIL;
+            self.pairu[v] = self.NIL;
+        }
+        if self.pairv[v] != self.NIL {
+            let u = self.pairv[v];
+            self.pairu[u] = self.NIL;
+            self.pairv[v] = self.NIL;
+        }
+    }
+
+    fn warm_start(&mut self, matching: &[i32]) {
+        for idx in 0..self.setu.len() {
+            let u = self.setu[idx];
+            if u >= matching.len() || matching[u] < 0 { continue; }
+            let v = matching[u] as usize;
+            if v >= self.NIL || self.pairu[u] != self.NIL || self.pairv[v] != self.NIL { continue; }
+            if !self.g[u].contains(&v) { continue; }
+            self.pairu[u] = v;
+            self.pairv[v] = u;
+        }
+    }
+
+    fn augment(&mut self) -> usize {
+        while self.bfs() {
+            for idx in 0..self.setu.len() {
+                let u = self.setu[idx];
+                if self.pairu[u] == self.NIL {
+                    self.dfs(u);
+                }
+            }
+        }
+        self.setu.iter()
+            .filter(|u| self.pairu[**u] != self.NIL)
+            .count()
+    }
 }
 
 #[cfg(test)]
@@ -229,6 +313,54 @@ mod tests {
         assert_eq!(got, 8);
     }
 
+    #[test]
+    fn incremental() {
+        let mut matcher = HopcroftKarp::new_from_graph(vec![Vec::new(); 4], vec![0, 1], vec![2, 3]);
+        matcher.add_edge(0, 2);
+        assert_eq!(matcher.augment(), 1);
+        matcher.add_edge(1, 2);
+        assert_eq!(matcher.augment(), 1);
+        matcher.add_edge(0, 3);
+        assert_eq!(matcher.augment(), 2);
+        assert_eq!(*matcher.get_matching(), vec![3, 2, 1, 0, -1]);
+
+        // new vertices are added on the fly
+        matcher.add_edge(4, 5);
+        assert_eq!(matcher.augment(), 3);
+        assert_eq!(*matcher.get_matching(), vec![3, 2, 1, 0, 5, 4, -1]);
+
+        matcher.remove_vertex(2);
+        assert_eq!(matcher.augment(), 2);
+        assert_eq!(*matcher.get_matching(), vec![3, -1, -1, 0, 5, 4, -1]);
+        matcher.remove_edge(4, 5);
+        assert_eq!(matcher.augment(), 1);
+        assert_eq!(*matcher.get_matching(), vec![3, -1, -1, 0, -1, -1, -1]);
+    }
+
+    #[test]
+    fn sides_of_new_vertices() {
+        // 1 and 2 are allocated by the first edge, but get their sides from the second
+        let mut matcher = HopcroftKarp::new();
+        matcher.add_edge(0, 3);
+        matcher.add_edge(1, 2);
+        assert_eq!(matcher.augment(), 2);
+        assert_eq!(*matcher.get_matching(), vec![3, 2, 1, 0, -1]);
+    }
+
+    #[test]
+    fn warm_start() {
+        let edges: Vec<(usize, usize)> = vec![(0, 3), (0, 4), (1, 3), (2, 5), (1, 5)];
+        let mut g: Vec<Vec<usize>> = vec![Vec::new(); 6];
+        for e in edges.iter() {
+            add_edge(&mut g, e.0, e.1);
+        }
+        let mut matcher = HopcroftKarp::new_from_graph(g.clone(), vec![0, 1, 2], vec![3, 4, 5]);
+        // pair (1, 4) is not an edge, (2, 5) is kept
+        matcher.warm_start(&[-1, 4, 5, -1, 1, 2]);
+        assert_eq!(*matcher.get_matching(), vec![-1, -1, 5, -1, -1, 2, -1]);
+        assert_eq!(matcher.augment(), 3);
+    }
+
     #[test]
     fn large_random() {
         let maxn = 250_000;
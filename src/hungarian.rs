@@ -0,0 +1,228 @@
+use crate::matching::WeightedMatcher;
+
+const INF: i64 = i64::MAX/4;
+
+// https://en.wikipedia.org/wiki/Hungarian_algorithm
+// O(n^2*m) version with potentials
+#[derive(Clone)]
+pub struct Hungarian {
+    pub cost: Vec<Vec<Option<i64>>>,
+    pub assignment: Vec<i32>,
+    pub total: i64,
+}
+
+impl Hungarian {
+    // rows are assigned to columns, requires n <= m and every pair allowed
+    // returns p, where p[column] = row+1 (0 -> column is free)
+    fn dense(cost: &[Vec<i64>]) -> Vec<usize> {
+        let n = cost.len();
+        let m = cost[0].len();
+
+        // 1-indexed, column 0 is a fake column used to start each phase
+        let mut u = vec![0; n+1];
+        let mut v = vec![0; m+1];
+        let mut p = vec![0; m+1];
+        let mut way = vec![0; m+1];
+        for i in 1..=n {
+            p[0] = i;
+            let mut j0 = 0;
+            let mut minv = vec![INF; m+1];
+            let mut used = vec![false; m+1];
+            loop {
+                used[j0] = true;
+                let i0 = p[j0];
+                let mut delta = INF;
+                let mut j1 = 0;
+                for j in 1..=m {
+                    if used[j] { continue; }
+                    let cur = cost[i0-1][j-1]-u[i0]-v[j];
+                    if cur < minv[j] {
+                        minv[j] = cur;
+                        way[j] = j0;
+                    }
+                    if minv[j] < delta {
+                        delta = minv[j];
+                        j1 = j;
+                    }
+                }
+                for j in 0..=m {
+                    if used[j] {
+                        u[p[j]] += delta;
+                        v[j] -= delta;
+                    }
+                    else {
+                        minv[j] -= delta;
+                    }
+                }
+                j0 = j1;
+                if p[j0] == 0 { break; }
+            }
+            // unwind augmenting path
+            loop {
+                let j1 = way[j0];
+                p[j0] = p[j1];
+                j0 = j1;
+                if j0 == 0 { break; }
+            }
+        }
+        p
+    }
+}
+
+impl WeightedMatcher for Hungarian {
+    fn new() -> Self {
+        Hungarian {
+            cost: Vec::new(),
+            assignment: Vec::new(),
+            total: 0,
+        }
+    }
+
+    fn init(&mut self, cost: Vec<Vec<Option<i64>>>) {
+        self.assignment = vec![-1; cost.len()];
+        self.cost = cost;
+        self.total = 0;
+    }
+
+    fn solve(&mut self) -> usize {
+        let n = self.cost.len();
+        self.assignment = vec![-1; n];
+        self.total = 0;
+        if n == 0 || self.cost[0].is_empty() { return 0; }
+        let m = self.cost[0].len();
+
+        // forbidden pairs get a cost larger than any sum of allowed pairs,
+        // so the optimum first maximises the number of allowed pairs
+        let big = self.cost.iter()
+            .flatten()
+            .flatten()
+            .map(|x| x.abs())
+            .sum::<i64>()+1;
+
+        // always assign the smaller side
+        let transpose = n > m;
+        let (rows, cols) = if transpose { (m, n) } else { (n, m) };
+        let mut dense = vec![vec![0; cols]; rows];
+        for (i, row) in dense.iter_mut().enumerate() {
+            for (j, val) in row.iter_mut().enumerate() {
+                let (u, v) = if transpose { (j, i) } else { (i, j) };
+                *val = self.cost[u][v].unwrap_or(big);
+            }
+        }
+
+        let mut matched = 0;
+        for (j, &i) in Self::dense(&dense).iter().enumerate().skip(1) {
+            if i == 0 { continue; }
+            let (u, v) = if transpose { (j-1, i-1) } else { (i-1, j-1) };
+            if let Some(c) = self.cost[u][v] {
+                self.assignment[u] = v as i32;
+                self.total += c;
+                matched += 1;
+            }
+        }
+        matched
+    }
+
+    fn get_cost(&self) -> i64 {
+        self.total
+    }
+
+    fn get_assignment(&self) -> &Vec<i32> {
+        &self.assignment
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+    use crate::hungarian::Hungarian;
+    use crate::matching::WeightedMatcher;
+
+    fn allowed(cost: Vec<Vec<i64>>) -> Vec<Vec<Option<i64>>> {
+        cost.into_iter().map(|row| row.into_iter().map(Some).collect()).collect()
+    }
+
+    // best (matched, cost) over all partial assignments, rows from idx onwards
+    fn brute(cost: &Vec<Vec<Option<i64>>>, idx: usize, used: &mut Vec<bool>) -> (usize, i64) {
+        if idx == cost.len() { return (0, 0); }
+        let mut best = brute(cost, idx+1, used);
+        for j in 0..used.len() {
+            if used[j] || cost[idx][j].is_none() { continue; }
+            used[j] = true;
+            let (cnt, sum) = brute(cost, idx+1, used);
+            used[j] = false;
+            let now = (cnt+1, sum+cost[idx][j].unwrap());
+            if now.0 > best.0 || (now.0 == best.0 && now.1 < best.1) {
+                best = now;
+            }
+        }
+        best
+    }
+
+    #[test]
+    fn simple() {
+        let mut hungarian = Hungarian::new();
+        hungarian.init(allowed(vec![vec![4, 1, 3], vec![2, 0, 5], vec![3, 2, 2]]));
+        assert_eq!(hungarian.solve(), 3);
+        assert_eq!(hungarian.get_cost(), 5);
+        assert_eq!(*hungarian.get_assignment(), vec![1, 0, 2]);
+    }
+
+    #[test]
+    fn more_columns() {
+        let mut hungarian = Hungarian::new();
+        hungarian.init(allowed(vec![vec![7, 3, 9, 1], vec![2, 8, 1, 6]]));
+        assert_eq!(hungarian.solve(), 2);
+        assert_eq!(hungarian.get_cost(), 2);
+        assert_eq!(*hungarian.get_assignment(), vec![3, 2]);
+    }
+
+    #[test]
+    fn more_rows() {
+        let mut hungarian = Hungarian::new();
+        hungarian.init(allowed(vec![vec![7, 2], vec![3, 8], vec![9, 1], vec![1, 6]]));
+        assert_eq!(hungarian.solve(), 2);
+        assert_eq!(hungarian.get_cost(), 2);
+        assert_eq!(*hungarian.get_assignment(), vec![-1, -1, 1, 0]);
+    }
+
+    #[test]
+    fn forbidden() {
+        // cheapest pairs are forbidden, so the matching has to go around them
+        let mut hungarian = Hungarian::new();
+        hungarian.init(vec![vec![None, Some(5)], vec![Some(7), Some(1)], vec![None, None]]);
+        assert_eq!(hungarian.solve(), 2);
+        assert_eq!(hungarian.get_cost(), 12);
+        assert_eq!(*hungarian.get_assignment(), vec![1, 0, -1]);
+    }
+
+    #[test]
+    fn random_against_brute_force() {
+        let mut rng = rand::thread_rng();
+        let mut hungarian = Hungarian::new();
+        for _ in 0..500 {
+            let n = rng.gen_range(1..6);
+            let m = rng.gen_range(1..6);
+            let cost = (0..n).map(|_| (0..m).map(|_| {
+                if rng.gen_bool(0.3) { None } else { Some(rng.gen_range(-20..50)) }
+            }).collect()).collect::<Vec<Vec<_>>>();
+
+            let exp = brute(&cost, 0, &mut vec![false; m]);
+            hungarian.init(cost.clone());
+            let got = hungarian.solve();
+            assert_eq!((got, hungarian.get_cost()), exp, "{:?}", cost);
+
+            // assignment has to be consistent with the reported cost
+            let assignment = hungarian.get_assignment();
+            let mut seen = vec![false; m];
+            let mut sum = 0;
+            for (u, &v) in assignment.iter().enumerate() {
+                if v == -1 { continue; }
+                assert!(!seen[v as usize]);
+                seen[v as usize] = true;
+                sum += cost[u][v as usize].unwrap();
+            }
+            assert_eq!(sum, exp.1);
+        }
+    }
+}
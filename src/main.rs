@@ -7,7 +7,13 @@ mod generate_gif;
 mod agent_strategies;
 mod target_strategies;
 mod flow;
+mod hungarian;
 mod bench;
+mod time_expanded;
+mod dimacs;
+mod game;
+mod trajectory;
+mod observation;
 
 use crate::map::*;
 use crate::runner::*;
@@ -17,6 +23,7 @@ use crate::matching::*;
 use crate::turbo::*;
 use crate::flow::*;
 use crate::bench::*;
+use crate::observation::*;
 use hopcroft_karp::HopcroftKarp;
 use rand::prelude::*;
 
@@ -40,6 +47,28 @@ fn main() {
         // AgentStrategies::NoCollisionFree,
     ];
 
+    // time expanded networks of NoCollisionFree solved with every backend
+    let flow_backends: Vec<FlowBackends> = vec![
+        // FlowBackends::FordFulkerson,
+        // FlowBackends::Dinic,
+        // FlowBackends::PushRelabel,
+    ];
+    let flow_horizon = 50;
+    // targets of every run, unless scripted
+    let target_template = TargetStrategyTemplate {
+        strategy: TargetStrategies::TargetFollowPath,
+        path_length: PathLength::Fixed(1000),
+        same_dir: SAME_DIR,
+        waypoints: Vec::new(),
+        motion: MarkovModel::Uniform,
+    };
+    // directory for DIMACS dumps of the first flow benchmark network of every map
+    let dimacs_dir: Option<&str> = None;
+    // what agents know about the targets in benchmarks (see observation.rs), everything if None
+    let observation: Option<ObservationModel> = None;
+    // scripted targets (see trajectory.rs) used in every run instead of generated paths
+    let trajectory_file: Option<&str> = None;
+
     let nruns = 10_000;
 
     for map_name in maps {
@@ -62,10 +91,52 @@ fn main() {
 
         let mut strategies: Vec<Box<dyn TargetStrategy>> = Vec::new();
 
-        for targets in &mut all_targets {
-            let target_strategy = TargetFollowPath::new(targets.len(), &map,
-                targets.iter().map(|x| x.position).collect(), targets, true, 1000);
-            strategies.push(Box::new(target_strategy));
+        let mut scripted = None;
+        if let Some(file_path) = trajectory_file {
+            match trajectory::load_trajectories(file_path, &map, d_time) {
+                Ok(res) => scripted = Some(res),
+                Err(s) => {
+                    println!("Failed to load trajectories: {}", s);
+                    return;
+                },
+            }
+        }
+
+        let mut solvers = GameSolvers::new();
+        for (agents, targets) in all_agents.iter().zip(all_targets.iter_mut()) {
+            if let Some((scripted_targets, target_strategy)) = &scripted {
+                *targets = scripted_targets.clone();
+                strategies.push(Box::new(target_strategy.clone()));
+                continue;
+            }
+            match target_template.construct(&map, agents, targets, d_time, &mut solvers) {
+                Ok(target_strategy) => strategies.push(target_strategy),
+                Err(s) => {
+                    println!("Failed to construct target strategy: {}", s);
+                    return;
+                },
+            }
+        }
+
+        if let Some(dir) = dimacs_dir {
+            let mut flow = Dinic::new();
+            NoCollisionFree::new().network(&mut flow, flow_horizon, &map, &all_agents[0], &all_targets[0]);
+            let file_path = format!("{}/{}_h{}.max", dir, map_name.trim_end_matches(".map"), flow_horizon);
+            if let Err(s) = dimacs::save_dimacs(&flow, &file_path) {
+                println!("Failed to save network: {}", s);
+            }
+        }
+
+        if !flow_backends.is_empty() {
+            match bench_flow(&map, flow_horizon, &all_agents, &all_targets, &flow_backends) {
+                Ok(results) => {
+                    for fr in results {
+                        println!("Flow benchmark finished! map: {}, backend: {:?}", map_name, fr.backend);
+                        println!("avg time: {:.4}ms", fr.avg_time);
+                    }
+                },
+                Err(s) => println!("Flow benchmark error: {}", s),
+            }
         }
 
         let mut collected: Vec<Vec<u64>> = Vec::new();
@@ -75,7 +146,9 @@ fn main() {
                 strategy: strat.clone(),
                 permutation: None,
                 matcher: Some(HopcroftKarp::new()),
-                flow: Some(FordFulkerson::new()),
+                flow: Some(FlowBackends::Dinic),
+                objective: Objective::Makespan,
+                motion: MarkovModel::Uniform,
             };
 
             for strat in &mut strategies {
@@ -83,7 +156,7 @@ fn main() {
             }
 
             let res = bench(&map, nruns as i32, d_time, all_agents.clone(),
-                            all_targets.clone(), agent_template, &mut strategies, false, true);
+                            all_targets.clone(), agent_template, &mut strategies, observation, false, true);
 
             match res {
                 Ok(br) => {
@@ -168,7 +241,7 @@ fn main() {
 
     let mut follow_path: Box<dyn TargetStrategy> =
         Box::new(TargetFollowPath::new(targets.len(), &map,
-        targets.iter().map(|x| x.position).collect(), &mut targets, true, 0));
+        targets.iter().map(|x| x.position).collect(), &mut targets, true, PathLength::Fixed(0), SAME_DIR));
 
     let perm = vec![1, 3, 2, 0];
     // let mut agent_strat = CollisionAssigned::new();
@@ -201,12 +274,12 @@ fn main() {
     ]), d_time);
 
     let mut follow_path = TargetFollowPath::new(targets.len(), &map,
-        targets.iter().map(|x| x.position).collect(), &mut targets, true, 20);
+        targets.iter().map(|x| x.position).collect(), &mut targets, true, PathLength::Fixed(20), SAME_DIR);
 
     let mut path = vec![Direction::West; 7];
     path.push(Direction::North);
     path.extend(vec![Direction::East; 7]);
-    // follow_path.set_path(0, &path, &map, &mut targets[0], false);
+    // follow_path.set_path(0, &path, &map, &mut targets[0], d_time, false);
 
     // let mut flow = FordFulkerson::new();
     let mut agent_strat = MakeSpanHopcroft {};
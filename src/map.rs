@@ -1,4 +1,4 @@
-use std::{fs, collections::{VecDeque, HashSet}};
+use std::{fs, cell::RefCell, collections::{VecDeque, HashSet, HashMap}};
 use std::cmp;
 use rand::Rng;
 
@@ -8,7 +8,7 @@ enum Tile {
     Free,
 }
 
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Direction {
     North,
     East,
@@ -118,6 +118,15 @@ pub struct Point {
     pub y: usize,
 }
 
+// how straight lines between cell centres are rasterised
+#[allow(dead_code)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum LineOfSight {
+    Bresenham,
+    // every cell the line touches, passing a corner needs both cells next to it, symmetric
+    Supercover,
+}
+
 pub fn go_direction(point: Point, direction: Direction) -> Point {
     match direction {
         Direction::North => Point{x: point.x, y: point.y+1},
@@ -128,6 +137,9 @@ pub fn go_direction(point: Point, direction: Direction) -> Point {
     }
 }
 
+// visible_set queries (cell, radius, rasterisation) and their answers
+type Visibility = HashMap<(Point, Option<usize>, LineOfSight), Vec<Point>>;
+
 // #[derive(Clone)]
 pub struct Map {
     pub height: usize,
@@ -135,6 +147,8 @@ pub struct Map {
     map: Vec<Vec<Tile>>,
     dist: Vec<Vec<usize>>,
     from: Vec<Vec<Direction>>,
+    // cells seen from a tile within a radius, only for the queries of visible_set
+    visibility: RefCell<Visibility>,
 }
 
 impl Map {
@@ -184,6 +198,7 @@ impl Map {
             map,
             dist: Vec::new(),
             from: Vec::new(),
+            visibility: RefCell::new(HashMap::new()),
         };
 
         let tiles = height*width;
@@ -274,16 +289,6 @@ impl Map {
         x < self.width && y < self.height && self.map[x][y] == Tile::Free
     }
 
-    pub fn reverse_direction(dir: &Direction) -> Direction {
-        match dir {
-            Direction::None => Direction::None,
-            Direction::North => Direction::South,
-            Direction::East => Direction::West,
-            Direction::South => Direction::North,
-            Direction::West => Direction::East,
-        }
-    }
-
     pub fn valid_direction(&self, p: Point, dir: Direction) -> bool {
         if p.y == self.height && dir == Direction::North { false }
         else if p.x == self.width && dir == Direction::East { false }
@@ -334,6 +339,95 @@ impl Map {
             return Direction::South;
         }
     }
+
+    // p1 and p2 see each other when all cells of the line between them are free
+    pub fn sees(&self, p1: &Point, p2: &Point, line: LineOfSight) -> bool {
+        match line {
+            LineOfSight::Bresenham => self.line_of_sight(p1, p2),
+            LineOfSight::Supercover => self.supercover(p1, p2),
+        }
+    }
+
+    // cells of the Bresenham line between p1 and p2 (both included) are all free
+    pub fn line_of_sight(&self, p1: &Point, p2: &Point) -> bool {
+        let (x2, y2) = (p2.x as i64, p2.y as i64);
+        let (mut x, mut y) = (p1.x as i64, p1.y as i64);
+        let dx = (x2-x).abs();
+        let dy = -(y2-y).abs();
+        let sx = if x < x2 { 1 } else { -1 };
+        let sy = if y < y2 { 1 } else { -1 };
+        let mut err = dx+dy;
+        loop {
+            if !self.valid_point_expl(x as usize, y as usize) { return false; }
+            if x == x2 && y == y2 { return true; }
+            let e2 = 2*err;
+            if e2 >= dy {
+                err += dy;
+                x += sx;
+            }
+            if e2 <= dx {
+                err += dx;
+                y += sy;
+            }
+        }
+    }
+
+    fn supercover(&self, p1: &Point, p2: &Point) -> bool {
+        let free = |x: i64, y: i64| self.valid_point_expl(x as usize, y as usize);
+        let (nx, ny) = ((p2.x as i64 - p1.x as i64).abs(), (p2.y as i64 - p1.y as i64).abs());
+        let sx = if p1.x < p2.x { 1 } else { -1 };
+        let sy = if p1.y < p2.y { 1 } else { -1 };
+        let (mut x, mut y) = (p1.x as i64, p1.y as i64);
+        if !free(x, y) { return false; }
+        let (mut ix, mut iy) = (0, 0);
+        while ix < nx || iy < ny {
+            // which cell border the line crosses next
+            let decision = (1 + 2*ix)*ny - (1 + 2*iy)*nx;
+            if decision == 0 {
+                if !free(x+sx, y) || !free(x, y+sy) { return false; }
+                x += sx;
+                y += sy;
+                ix += 1;
+                iy += 1;
+            }
+            else if decision < 0 {
+                x += sx;
+                ix += 1;
+            }
+            else {
+                y += sy;
+                iy += 1;
+            }
+            if !free(x, y) { return false; }
+        }
+        true
+    }
+
+    // cells seen from p within the euclidean radius, cached per query
+    pub fn visible_set(&self, p: &Point, radius: Option<usize>, line: LineOfSight) -> Vec<Point> {
+        if !self.valid_point(p) { return Vec::new(); }
+        let key = (*p, radius, line);
+        if let Some(res) = self.visibility.borrow().get(&key) {
+            return res.clone();
+        }
+        let (xs, ys) = match radius {
+            Some(r) => (p.x.saturating_sub(r)..cmp::min(p.x+r+1, self.width),
+                        p.y.saturating_sub(r)..cmp::min(p.y+r+1, self.height)),
+            None => (0..self.width, 0..self.height),
+        };
+        let mut res = Vec::new();
+        for x in xs {
+            for y in ys.clone() {
+                let (dx, dy) = (p.x.abs_diff(x), p.y.abs_diff(y));
+                let q = Point{x, y};
+                if radius.is_none_or(|r| dx*dx + dy*dy <= r*r) && self.sees(p, &q, line) {
+                    res.push(q);
+                }
+            }
+        }
+        self.visibility.borrow_mut().insert(key, res.clone());
+        res
+    }
 }
 
 pub fn print_board(map: &Map, agents: &Vec<Agent>, targets: &Vec<Target>) {
@@ -449,4 +543,53 @@ mod tests {
         assert_eq!(exp, map.from[map.conv(1, 1)]);
     }
 
+    #[test]
+    fn test_line_of_sight() {
+        let map = Map::new("resources/maps/example.map");
+        assert!(map.line_of_sight(&Point{x: 1, y: 1}, &Point{x: 3, y: 1}));
+        assert!(map.line_of_sight(&Point{x: 3, y: 1}, &Point{x: 1, y: 1}));
+        assert!(map.line_of_sight(&Point{x: 2, y: 3}, &Point{x: 2, y: 3}));
+        // around the wall in the middle
+        assert!(!map.line_of_sight(&Point{x: 1, y: 1}, &Point{x: 3, y: 3}));
+        assert!(!map.line_of_sight(&Point{x: 2, y: 1}, &Point{x: 2, y: 3}));
+        assert!(!map.line_of_sight(&Point{x: 1, y: 1}, &Point{x: 0, y: 0}));
+    }
+
+    #[test]
+    fn test_visibility() {
+        let map = Map::new("resources/maps/example.map");
+        // the line touches the corner of the wall in the middle
+        let (p, q) = (Point{x: 1, y: 2}, Point{x: 2, y: 3});
+        assert!(map.sees(&p, &q, LineOfSight::Bresenham));
+        assert!(!map.sees(&p, &q, LineOfSight::Supercover));
+        assert!(map.visible_set(&p, None, LineOfSight::Bresenham).contains(&q));
+        assert!(!map.visible_set(&p, None, LineOfSight::Supercover).contains(&q));
+
+        let corner = Point{x: 1, y: 1};
+        let mut seen = map.visible_set(&corner, None, LineOfSight::Supercover);
+        seen.sort_by_key(|p| (p.x, p.y));
+        assert_eq!(seen, vec![Point{x: 1, y: 1}, Point{x: 1, y: 2}, Point{x: 1, y: 3}, Point{x: 2, y: 1}, Point{x: 3, y: 1}]);
+        assert_eq!(map.visible_set(&corner, Some(1), LineOfSight::Supercover).len(), 3);
+        assert!(map.visible_set(&Point{x: 2, y: 2}, None, LineOfSight::Supercover).is_empty());
+
+        let map = Map::new("resources/maps/simple.map");
+        for x1 in 0..map.width {
+            for y1 in 0..map.height {
+                let p = Point{x: x1, y: y1};
+                let seen = map.visible_set(&p, None, LineOfSight::Supercover);
+                let near = map.visible_set(&p, Some(2), LineOfSight::Supercover);
+                for x2 in 0..map.width {
+                    for y2 in 0..map.height {
+                        let q = Point{x: x2, y: y2};
+                        let sees = map.sees(&p, &q, LineOfSight::Supercover);
+                        assert_eq!(sees, map.sees(&q, &p, LineOfSight::Supercover), "{:?} {:?}", p, q);
+                        assert_eq!(seen.contains(&q), map.valid_point(&p) && sees);
+                        let within = x1.abs_diff(x2).pow(2) + y1.abs_diff(y2).pow(2) <= 4;
+                        assert_eq!(near.contains(&q), seen.contains(&q) && within);
+                    }
+                }
+            }
+        }
+    }
+
 }
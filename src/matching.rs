@@ -1,74 +1,172 @@
+use std::collections::BTreeSet;
+
 use super::map::*;
 
 pub trait Matcher {
+    #[allow(dead_code)]
     fn new_from_graph(graph: Vec<Vec<usize>>, setu: Vec<usize>, setv: Vec<usize>) -> Self;
     fn new() -> Self;
     fn init(&mut self, graph: Vec<Vec<usize>>, setu: Vec<usize>, setv: Vec<usize>);
 
     // returns matching size
+    #[allow(dead_code)]
     fn solve(&mut self) -> usize;
 
     // returns vector with selected vertices
     fn get_matching(&mut self) -> &Vec<i32>;
+
+    // incremental api, graph and matching are kept between calls
+
+    // adds edge between u (from setu) and v (from setv), new vertices are added if needed
+    fn add_edge(&mut self, u: usize, v: usize);
+
+    // removes edge between u (from setu) and v (from setv), if matched both become free
+    fn remove_edge(&mut self, u: usize, v: usize);
+
+    // removes all edges of v, its pair becomes free
+    #[allow(dead_code)]
+    fn remove_vertex(&mut self, v: usize);
+
+    // seeds matching with pairs from previous matching (in get_matching format),
+    // pairs that are not edges of the current graph or collide are skipped
+    fn warm_start(&mut self, matching: &[i32]);
+
+    // extends current matching with augmenting paths, returns matching size
+    fn augment(&mut self) -> usize;
 }
 
-pub fn makespan_solve(map: &Map, agents: &Vec<Point>, targets: &Vec<Point>, matcher: &mut impl Matcher) -> i32 {
-    let mut left: i32 = 0;
-    let mut right: i32 = 1_000_000_000;
+// min cost assignment on a (possibly rectangular) cost matrix
+pub trait WeightedMatcher {
+    fn new() -> Self;
+    // cost[u][v] of assigning u to v, None -> pair is forbidden
+    fn init(&mut self, cost: Vec<Vec<Option<i64>>>);
 
-    let n = agents.len();
-    let m = targets.len();
-    let mut res = -1;
+    // returns matching size, which is always maximal; among maximal matchings
+    // the one with the smallest cost is selected
+    fn solve(&mut self) -> usize;
+
+    // returns cost of the found matching
+    #[allow(dead_code)]
+    fn get_cost(&self) -> i64;
 
-    while left <= right {
+    // returns vector with selected v for every u (-1 if u is not matched)
+    fn get_assignment(&self) -> &Vec<i32>;
+}
+
+// first of the sorted distinct weights for which matches(weight) (size of the maximum
+// matching using edges up to weight) reaches need, binary searched so only O(log W)
+// matchings are found, -1 if there is none
+// the matcher is left with the matching of the returned weight (all edges for -1)
+fn threshold_search(edges: &[(usize, usize, i32)], need: usize, mut matches: impl FnMut(i32) -> usize) -> i32 {
+    if need == 0 {
+        matches(i32::MIN);
+        return 0;
+    }
+    let mut weights = edges.iter().map(|e| e.2).collect::<Vec<_>>();
+    weights.sort();
+    weights.dedup();
+
+    let (mut left, mut right) = (0, weights.len());
+    let mut last = None;
+    while left < right {
         let mid = left+(right-left)/2;
-        // println!("trying... {}", mid);
-        let mut graph: Vec<Vec<usize>> = vec![Vec::new(); n+m];
-        for (i, agent) in agents.iter().enumerate() {
-            for (j, target) in targets.iter().enumerate() {
-                if map.dist_point(&agent, &target) <= mid as usize {
-                    graph[i].push(j+n);
-                    graph[j+n].push(i);
-                }
-            }
-        }
+        last = Some(mid);
+        if matches(weights[mid]) == need { right = mid; }
+        else { left = mid+1; }
+    }
+    if left == weights.len() {
+        if last.is_none_or(|x| x+1 != weights.len()) { matches(i32::MAX); }
+        return -1;
+    }
+    if last != Some(left) { matches(weights[left]); }
+    weights[left]
+}
 
-        let setu = (0..agents.len()).collect();
-        let setv = (agents.len()..agents.len()+targets.len()).collect();
-        // println!("graph: {:?}", graph);
-        // println!("setu: {:?}", setu);
-        // println!("setv: {:?}", setv);
-        // println!("matching: {:?}", matcher.get_matching());
-        matcher.init(graph, setu, setv);
-        let got = matcher.solve();
-        if got == std::cmp::min(n, m) {
-            res = mid;
-            right = mid-1;
+// bottleneck assignment: finds maximum matching minimising the largest used weight
+// edges are (u, v, weight) with u in 0..n and v in 0..m, they are added to one matcher
+// in order of weight and the matching is extended after every weight
+// hint is a matching from previous call (in get_matching format, can be empty),
+// its pairs seed the matching
+// returns bottleneck, or -1 if there is no matching of size min(n, m)
+// matcher is left with the optimal matching (v are shifted by n), or with
+// a maximum matching of all edges for -1
+pub fn bottleneck_solve(n: usize, m: usize, mut edges: Vec<(usize, usize, i32)>,
+                        matcher: &mut impl Matcher, hint: &[i32]) -> i32 {
+    matcher.init(vec![Vec::new(); n+m], (0..n).collect(), (n..n+m).collect());
+    let need = std::cmp::min(n, m);
+    if need == 0 { return 0; }
+    edges.sort_by_key(|e| e.2);
+    let mut idx = 0;
+    while idx < edges.len() {
+        let weight = edges[idx].2;
+        while idx < edges.len() && edges[idx].2 == weight {
+            matcher.add_edge(edges[idx].0, edges[idx].1+n);
+            idx += 1;
         }
-        else {
-            left = mid+1;
+        if !hint.is_empty() { matcher.warm_start(hint); }
+        if matcher.augment() == need { return weight; }
+    }
+    -1
+}
+
+// bottleneck assignment kept between calls (turns of MakeSpanHopcroft and Pibt),
+// the matcher keeps the graph of the last probed threshold with its matching, every
+// probe only adds and removes the edges that changed and extends the matching,
+// so when little moved between turns only a few augmenting paths are needed
+pub struct Bottleneck<M: Matcher> {
+    pub matcher: M,
+    n: usize,
+    m: usize,
+    // (u, v) pairs that are edges of matcher
+    edges: BTreeSet<(usize, usize)>,
+}
+
+impl<M: Matcher> Bottleneck<M> {
+    pub fn new() -> Self {
+        Bottleneck { matcher: M::new(), n: 0, m: 0, edges: BTreeSet::new() }
+    }
+
+    // same as bottleneck_solve, the matcher is only rebuilt when n or m changes
+    pub fn solve(&mut self, n: usize, m: usize, edges: &[(usize, usize, i32)], hint: &[i32]) -> i32 {
+        if (n, m) != (self.n, self.m) {
+            self.matcher.init(vec![Vec::new(); n+m], (0..n).collect(), (n..n+m).collect());
+            self.edges.clear();
+            (self.n, self.m) = (n, m);
         }
+        let matcher = &mut self.matcher;
+        let current = &mut self.edges;
+        threshold_search(edges, std::cmp::min(n, m), |threshold| {
+            let wanted = edges.iter()
+                .filter(|e| e.2 <= threshold)
+                .map(|e| (e.0, e.1))
+                .collect::<BTreeSet<_>>();
+            for &(u, v) in current.difference(&wanted) { matcher.remove_edge(u, v+n); }
+            for &(u, v) in wanted.difference(current) { matcher.add_edge(u, v+n); }
+            *current = wanted;
+            if !hint.is_empty() { matcher.warm_start(hint); }
+            matcher.augment()
+        })
     }
+}
 
-    // bring back matcher to state with optimal mid
-    if res != -1 {
-        let mut graph: Vec<Vec<usize>> = vec![Vec::new(); n+m];
-        for (i, agent) in agents.iter().enumerate() {
-            for (j, target) in targets.iter().enumerate() {
-                if map.dist_point(&agent, &target) <= res as usize {
-                    graph[i].push(j+n);
-                    graph[j+n].push(i);
-                }
+// (agent, target, distance) for every reachable pair
+pub fn makespan_edges(map: &Map, agents: &[Point], targets: &[Point]) -> Vec<(usize, usize, i32)> {
+    let mut edges = Vec::new();
+    for (i, agent) in agents.iter().enumerate() {
+        for (j, target) in targets.iter().enumerate() {
+            let dist = map.dist_point(agent, target);
+            if dist <= 1_000_000_000 {
+                edges.push((i, j, dist as i32));
             }
         }
-
-        let setu = (0..agents.len()).collect();
-        let setv = (agents.len()..agents.len()+targets.len()).collect();
-        matcher.init(graph, setu, setv);
-        _ = matcher.solve();
     }
+    edges
+}
 
-    res
+// makespan of the agents from scratch, kept for the tests
+#[cfg(test)]
+pub fn makespan_solve(map: &Map, agents: &Vec<Point>, targets: &Vec<Point>, matcher: &mut impl Matcher) -> i32 {
+    bottleneck_solve(agents.len(), targets.len(), makespan_edges(map, agents, targets), matcher, &[])
 }
 
 #[cfg(test)]
@@ -76,6 +174,7 @@ mod tests {
     use crate::hopcroft_karp::HopcroftKarp;
     use crate::turbo::TurboMatching;
     use crate::map::*;
+    use rand::Rng;
 
     use super::*;
 
@@ -101,8 +200,140 @@ mod tests {
             dist: Vec::new(),
             matching: Vec::new(),
             NIL: 0,
+            registered: Vec::new(),
         };
         let map = Map::new("resources/maps/example.map");
         assert_eq!(makespan_solve(&map, &agents, &targets, &mut matcher), 3);
     }
+
+    // reference: binary search over the threshold with a full rematch every time
+    fn bottleneck_binary_search(n: usize, m: usize, edges: &Vec<(usize, usize, i32)>) -> i32 {
+        let mut left = 0;
+        let mut right = 1_000;
+        let mut res = -1;
+        while left <= right {
+            let mid = left+(right-left)/2;
+            let mut graph: Vec<Vec<usize>> = vec![Vec::new(); n+m];
+            for &(u, v, w) in edges.iter() {
+                if w <= mid {
+                    graph[u].push(v+n);
+                    graph[v+n].push(u);
+                }
+            }
+            let mut matcher = HopcroftKarp::new_from_graph(graph, (0..n).collect(), (n..n+m).collect());
+            if matcher.solve() == std::cmp::min(n, m) {
+                res = mid;
+                right = mid-1;
+            }
+            else {
+                left = mid+1;
+            }
+        }
+        res
+    }
+
+    #[test]
+    fn bottleneck_simple() {
+        let edges = vec![(0, 0, 5), (0, 1, 1), (1, 1, 2), (1, 0, 7)];
+        let mut matcher = HopcroftKarp::new();
+        assert_eq!(bottleneck_solve(2, 2, edges, &mut matcher, &[]), 5);
+        assert_eq!(*matcher.get_matching(), vec![2, 3, 0, 1, -1]);
+        assert_eq!(bottleneck_solve(2, 2, vec![(0, 0, 1), (1, 0, 1)], &mut matcher, &[]), -1);
+    }
+
+    #[test]
+    fn bottleneck_random() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..500 {
+            let n = rng.gen_range(0..8);
+            let m = rng.gen_range(0..8);
+            let mut edges = Vec::new();
+            for u in 0..n {
+                for v in 0..m {
+                    if rng.gen_bool(0.6) { edges.push((u, v, rng.gen_range(0..30))); }
+                }
+            }
+
+            let exp = bottleneck_binary_search(n, m, &edges);
+            let mut hopcroft = HopcroftKarp::new();
+            let mut turbo = TurboMatching::new();
+            assert_eq!(bottleneck_solve(n, m, edges.clone(), &mut hopcroft, &[]), exp);
+            assert_eq!(bottleneck_solve(n, m, edges.clone(), &mut turbo, &[]), exp);
+            if exp == -1 { continue; }
+
+            let matching = hopcroft.get_matching().clone();
+            let used = (0..n).filter(|u| matching[*u] != -1).count();
+            assert_eq!(used, std::cmp::min(n, m));
+            for (u, &pair) in matching.iter().enumerate().take(n) {
+                if pair == -1 { continue; }
+                let v = pair as usize-n;
+                assert!(edges.iter().any(|e| e.0 == u && e.1 == v && e.2 <= exp));
+            }
+
+            // warm start from previous matching gives the same optimum
+            assert_eq!(bottleneck_solve(n, m, edges.clone(), &mut hopcroft, &matching), exp);
+            assert_eq!(bottleneck_solve(n, m, edges.clone(), &mut turbo, &matching), exp);
+        }
+    }
+
+    #[test]
+    fn bottleneck_kept_between_calls() {
+        let mut rng = rand::thread_rng();
+        let mut hopcroft: Bottleneck<HopcroftKarp> = Bottleneck::new();
+        let mut turbo: Bottleneck<TurboMatching> = Bottleneck::new();
+        let mut edges = Vec::new();
+        for _ in 0..300 {
+            // weights drift a little every call, sizes change now and then
+            let n = if rng.gen_bool(0.1) || edges.is_empty() { rng.gen_range(1..7) } else { hopcroft.n };
+            let m = if rng.gen_bool(0.1) || edges.is_empty() { rng.gen_range(1..7) } else { hopcroft.m };
+            if (n, m) != (hopcroft.n, hopcroft.m) {
+                edges = Vec::new();
+                for u in 0..n {
+                    for v in 0..m {
+                        if rng.gen_bool(0.7) { edges.push((u, v, rng.gen_range(0..20))); }
+                    }
+                }
+            }
+            for e in edges.iter_mut() {
+                e.2 = std::cmp::max(0, e.2+rng.gen_range(-1..=1));
+            }
+
+            let exp = bottleneck_binary_search(n, m, &edges);
+            assert_eq!(hopcroft.solve(n, m, &edges, &[]), exp);
+            assert_eq!(turbo.solve(n, m, &edges, &[]), exp);
+            if exp == -1 { continue; }
+            let matching = hopcroft.matcher.get_matching().clone();
+            for (u, &pair) in matching.iter().enumerate().take(n) {
+                if pair == -1 { continue; }
+                assert!(edges.iter().any(|e| e.0 == u && e.1+n == pair as usize && e.2 <= exp));
+            }
+        }
+    }
+
+    #[test]
+    fn makespan_random_points() {
+        let map = Map::new("resources/maps/box.map");
+        for _ in 0..100 {
+            let agents = agents_random(&map, 5).iter().map(|x| x.position).collect::<Vec<_>>();
+            let targets = agents_random(&map, 4).iter().map(|x| x.position).collect::<Vec<_>>();
+            let mut edges = Vec::new();
+            for (i, agent) in agents.iter().enumerate() {
+                for (j, target) in targets.iter().enumerate() {
+                    let dist = map.dist_point(agent, target);
+                    // unreachable pairs are not edges (usize::MAX would wrap to -1)
+                    if dist != usize::MAX { edges.push((i, j, dist as i32)); }
+                }
+            }
+            let mut matcher = HopcroftKarp::new();
+            let got = makespan_solve(&map, &agents, &targets, &mut matcher);
+            assert_eq!(got, bottleneck_binary_search(5, 4, &edges));
+
+            // matcher is left with a matching that achieves it
+            let matching = matcher.get_matching().clone();
+            for i in 0..5 {
+                if matching[i] == -1 { continue; }
+                assert!(map.dist_point(&agents[i], &targets[matching[i] as usize-5]) as i32 <= got);
+            }
+        }
+    }
 }
@@ -0,0 +1,205 @@
+use std::collections::HashSet;
+use crate::map::*;
+
+// how far agents see targets
+#[allow(dead_code)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Sensing {
+    Full,
+    // euclidean distance, walls do not block
+    Radius(usize),
+    // free line between the cells (see Map::sees), optionally within a radius
+    LineOfSight(LineOfSight, Option<usize>),
+}
+
+// partial observability in Runner, agents get copies of the targets they see
+// (future paths are never revealed), with shared knowledge every agent gets
+// the targets seen by the whole team
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ObservationModel {
+    pub sensing: Sensing,
+    pub shared: bool,
+}
+
+pub struct Observation {
+    pub shared: bool,
+    // targets seen by every agent
+    pub seen: Vec<Vec<Target>>,
+}
+
+impl Observation {
+    // targets seen by some agent, in the order of Runner::targets
+    pub fn team(&self) -> Vec<Target> {
+        let mut res: Vec<Target> = Vec::new();
+        for target in self.seen.iter().flatten() {
+            if !res.iter().any(|x| x.idx == target.idx) {
+                res.push(target.clone());
+            }
+        }
+        res.sort_by_key(|x| x.idx);
+        res
+    }
+
+    // what agent idx knows about
+    pub fn known(&self, idx: usize) -> Vec<Target> {
+        if self.shared { self.team() } else { self.seen[idx].clone() }
+    }
+}
+
+impl ObservationModel {
+    pub fn sees(&self, map: &Map, from: &Point, to: &Point) -> bool {
+        let within = |radius: usize| {
+            let dx = from.x.abs_diff(to.x);
+            let dy = from.y.abs_diff(to.y);
+            dx*dx + dy*dy <= radius*radius
+        };
+        match self.sensing {
+            Sensing::Full => true,
+            Sensing::Radius(radius) => within(radius),
+            Sensing::LineOfSight(line, radius) => radius.is_none_or(within) && map.sees(from, to, line),
+        }
+    }
+
+    pub fn observe(&self, map: &Map, agents: &[Agent], targets: &[Target]) -> Observation {
+        let seen = agents.iter().map(|agent| {
+            targets.iter()
+                .filter(|target| agent.active && self.sees(map, &agent.position, &target.position))
+                .map(|target| Target { path: None, ..target.clone() })
+                .collect()
+        }).collect();
+        Observation { shared: self.shared, seen }
+    }
+
+    // free cells some active agent sees, empty with full sensing (nothing to shade)
+    pub fn seen_cells(&self, map: &Map, agents: &[Agent]) -> HashSet<Point> {
+        let mut res = HashSet::new();
+        if self.sensing == Sensing::Full { return res; }
+        for agent in agents.iter().filter(|x| x.active) {
+            match self.sensing {
+                Sensing::LineOfSight(line, radius) => res.extend(map.visible_set(&agent.position, radius, line)),
+                _ => {
+                    for x in 0..map.width {
+                        for y in 0..map.height {
+                            let p = Point{x, y};
+                            if map.valid_point(&p) && self.sees(map, &agent.position, &p) { res.insert(p); }
+                        }
+                    }
+                },
+            }
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::observation::*;
+    use crate::agent_strategies::*;
+    use crate::target_strategies::*;
+    use crate::runner::Runner;
+    use crate::bench::*;
+
+    #[test]
+    fn observe() {
+        let map = Map::new("resources/maps/simple.map");
+        let agents = agents_from(&vec![Point{x: 1, y: 1}, Point{x: 9, y: 3}]);
+        let mut targets = targets_from(&vec![Point{x: 1, y: 3}, Point{x: 3, y: 3}, Point{x: 9, y: 1}], 3);
+        for (idx, target) in targets.iter_mut().enumerate() {
+            target.idx = idx;
+            target.path = Some(vec![target.position]);
+        }
+        let idxs = |targets: &Vec<Target>| targets.iter().map(|x| x.idx).collect::<Vec<_>>();
+
+        let full = ObservationModel { sensing: Sensing::Full, shared: false }.observe(&map, &agents, &targets);
+        assert_eq!(idxs(&full.known(0)), vec![0, 1, 2]);
+        assert!(full.seen[0].iter().all(|x| x.path.is_none()));
+
+        let radius = ObservationModel { sensing: Sensing::Radius(2), shared: false }.observe(&map, &agents, &targets);
+        assert_eq!(idxs(&radius.known(0)), vec![0]);
+        assert_eq!(idxs(&radius.known(1)), vec![2]);
+        assert_eq!(idxs(&radius.team()), vec![0, 2]);
+        let radius = ObservationModel { sensing: Sensing::Radius(3), shared: false }.observe(&map, &agents, &targets);
+        assert_eq!(idxs(&radius.known(0)), vec![0, 1]);
+
+        // walls at (2, 2), (4, 1) and (6, 3) are in the way
+        let sight = ObservationModel { sensing: Sensing::LineOfSight(LineOfSight::Supercover, None), shared: true }.observe(&map, &agents, &targets);
+        assert_eq!(idxs(&sight.seen[0]), vec![0]);
+        assert_eq!(idxs(&sight.seen[1]), vec![2]);
+        assert_eq!(idxs(&sight.known(0)), vec![0, 2]);
+        let sight = ObservationModel { sensing: Sensing::LineOfSight(LineOfSight::Supercover, Some(1)), shared: true }.observe(&map, &agents, &targets);
+        assert!(sight.team().is_empty());
+    }
+
+    #[test]
+    fn seen_cells() {
+        let map = Map::new("resources/maps/example.map");
+        let agents = agents_from(&vec![Point{x: 1, y: 1}]);
+        let cells = |sensing| ObservationModel { sensing, shared: false }.seen_cells(&map, &agents);
+        assert!(cells(Sensing::Full).is_empty());
+        // the wall in the middle hides the far corner
+        assert_eq!(cells(Sensing::LineOfSight(LineOfSight::Supercover, None)).len(), 5);
+        // the line from (1, 2) to (2, 3) touches the corner of the wall
+        let (p, q) = (Point{x: 1, y: 2}, Point{x: 2, y: 3});
+        let sees = |line| ObservationModel { sensing: Sensing::LineOfSight(line, None), shared: false }.sees(&map, &p, &q);
+        assert!(sees(LineOfSight::Bresenham));
+        assert!(!sees(LineOfSight::Supercover));
+        assert_eq!(cells(Sensing::Radius(2)).len(), 5);
+        assert_eq!(cells(Sensing::Radius(3)).len(), 8);
+    }
+
+    // two agents in the corners of box.map, targets stand still
+    fn game(map: &Map) -> (Vec<Agent>, Vec<Target>, Box<dyn TargetStrategy>) {
+        let agents = agents_from(&vec![Point{x: 1, y: 1}, Point{x: 8, y: 8}]);
+        let mut targets = targets_from(&vec![Point{x: 2, y: 2}, Point{x: 1, y: 8}], 3);
+        for (idx, target) in targets.iter_mut().enumerate() { target.idx = idx; }
+        let target_strat: Box<dyn TargetStrategy> = Box::new(TargetFollowPath::new(2, map,
+            targets.iter().map(|x| x.position).collect(), &mut targets, false, PathLength::Fixed(0), SAME_DIR));
+        (agents, targets, target_strat)
+    }
+
+    fn run(observation: Option<ObservationModel>) -> (i32, usize) {
+        let map = Map::new("resources/maps/box.map");
+        let (agents, targets, mut target_strat) = game(&map);
+        let mut runner = Runner { map: &map, agents, targets, d_time: 3, observation };
+        let turns = runner.run(Box::new(MakeSpanHopcroft::new()), &mut target_strat, false, false, false, false, "", 50);
+        (turns, runner.targets.len())
+    }
+
+    #[test]
+    fn runner_with_observations() {
+        let full = run(None);
+        assert_eq!(full, (7, 0));
+        assert_eq!(run(Some(ObservationModel { sensing: Sensing::Full, shared: false })), full);
+        assert_eq!(run(Some(ObservationModel { sensing: Sensing::Radius(20), shared: false })), full);
+        // the second agent sees only its own target
+        assert_eq!(run(Some(ObservationModel { sensing: Sensing::Radius(7), shared: false })).1, 0);
+        // nobody sees the second target
+        assert_eq!(run(Some(ObservationModel { sensing: Sensing::Radius(2), shared: true })), (50, 1));
+    }
+
+    #[test]
+    fn joint_strategies_need_shared_observations() {
+        let map = Map::new("resources/maps/box.map");
+        let unshared = Some(ObservationModel { sensing: Sensing::Radius(20), shared: false });
+        let (agents, targets, mut target_strat) = game(&map);
+        let mut runner = Runner { map: &map, agents: agents.clone(), targets: targets.clone(), d_time: 3,
+                                  observation: unshared };
+        assert!(runner.check(&MakeSpanHopcroft::new()).is_ok());
+        assert!(runner.check(&Pibt::new()).is_err());
+        // refused games are not finished
+        assert_eq!(runner.run(Box::new(Pibt::new()), &mut target_strat, false, false, false, false, "", 50), 50);
+        assert_eq!(runner.targets.len(), 2);
+
+        let template = AgentStrategyTemplate {
+            strategy: AgentStrategies::Pibt,
+            permutation: None,
+            matcher: None,
+            flow: None,
+            objective: Objective::Makespan,
+            motion: MarkovModel::Uniform,
+        };
+        let res = bench(&map, 1, 3, vec![agents], vec![targets], template, &mut vec![target_strat],
+                        unshared, false, false);
+        assert!(res.is_err());
+    }
+}
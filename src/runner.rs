@@ -5,19 +5,37 @@ use crate::map::*;
 use crate::generate_gif::*;
 use crate::agent_strategies::*;
 use crate::target_strategies::*;
+use crate::observation::ObservationModel;
 
 pub struct Runner<'a> {
     pub map: &'a Map,
     pub agents: Vec<Agent>,
     pub targets: Vec<Target>,
     pub d_time: i32,
+    // agents only know what they observe, ground truth if None
+    pub observation: Option<ObservationModel>,
 }
 
 impl Runner<'_> {
+    // agents of strategies that plan together need shared observations
+    pub fn check(&self, agent_strat: &dyn AgentStrategy) -> Result<(), String> {
+        match self.observation {
+            Some(model) if !model.shared && !agent_strat.observes_alone() =>
+                Err("agents plan together, observations have to be shared".to_string()),
+            _ => Ok(()),
+        }
+    }
+
     pub fn run(&mut self, mut agent_strat: Box<dyn AgentStrategy>, target_strat: &mut Box<dyn TargetStrategy>,
             debug_printing: bool, enable_runtime_checks: bool, enable_gif: bool, print_res: bool, gif_path: &str,
             MAX_ITER: i32) -> i32 {
 
+        // refused games are not finished
+        if let Err(s) = self.check(agent_strat.as_ref()) {
+            println!("{}", s);
+            return MAX_ITER;
+        }
+
         let start = Instant::now();
         let mut frames: Vec<Vec<u8>> = Vec::new();
         if debug_printing {
@@ -42,7 +60,13 @@ impl Runner<'_> {
                 }
             }
 
-            let agent_dirs = agent_strat.pick(&self.map, &mut self.agents, &self.targets);
+            let agent_dirs = match &self.observation {
+                Some(model) => {
+                    let observation = model.observe(self.map, &self.agents, &self.targets);
+                    agent_strat.pick_observed(self.map, &mut self.agents, &observation)
+                },
+                None => agent_strat.pick(self.map, &mut self.agents, &self.targets),
+            };
             for (idx, dir) in agent_dirs.iter().enumerate() {
                 self.agents[idx].position = go_direction(self.agents[idx].position, *dir);
             }
@@ -112,7 +136,8 @@ impl Runner<'_> {
                 print_board(&self.map, &self.agents, &self.targets);
             }
             if enable_gif {
-                let frame = generate_frame(&self.map, &self.agents, &self.targets);
+                let seen = self.observation.map_or(HashSet::new(), |model| model.seen_cells(self.map, &self.agents));
+                let frame = generate_frame(self.map, &self.agents, &self.targets, &seen);
                 frames.push(frame);
             }
         }
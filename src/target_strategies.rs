@@ -1,14 +1,21 @@
 use rand::seq::SliceRandom;
-use std::collections::HashSet;
+use rand::Rng;
+use std::collections::{HashMap, HashSet};
 use std::cmp;
+use std::rc::Rc;
 
 use crate::map::*;
+use crate::game::GameSolver;
 
 #[allow(dead_code)]
 pub enum TargetStrategies {
     RandomTarget,
     MaximizeMinDist,
     TargetFollowPath,
+    Evader,
+    Optimal,
+    Patrol,
+    Markov,
 }
 
 pub trait TargetStrategy {
@@ -16,6 +23,7 @@ pub trait TargetStrategy {
     fn flush(&mut self);
 }
 
+// moves uniformly over the valid directions (resting included), same as MarkovModel::Uniform
 pub struct RandomTarget;
 impl TargetStrategy for RandomTarget {
     fn pick(&mut self, map: &Map, agents: &Vec<Agent>, targets: &Vec<Target>) -> Vec<Direction> {
@@ -43,6 +51,34 @@ impl TargetStrategy for RandomTarget {
     fn flush(&mut self) {}
 }
 
+// length of generated target paths
+#[allow(dead_code)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PathLength {
+    Fixed(usize),
+    Uniform(usize, usize), // inclusive range
+    Geometric(f64),        // stops before every step with probability p, mean (1-p)/p
+}
+
+impl PathLength {
+    pub fn sample(&self, rng: &mut impl Rng) -> usize {
+        match *self {
+            PathLength::Fixed(len) => len,
+            PathLength::Uniform(lo, hi) => rng.gen_range(lo..=hi),
+            PathLength::Geometric(p) => {
+                assert!(p > 0.0 && p <= 1.0, "stopping probability has to be in (0, 1]");
+                let mut len = 0;
+                while !rng.gen_bool(p) { len += 1; }
+                len
+            },
+        }
+    }
+}
+
+// default weight of repeating the previous move in generated paths, unbiased so
+// benchmarks keep the random walks of earlier versions (the bias never applied there)
+pub const SAME_DIR: f64 = 1.0;
+
 #[derive(Clone)]
 pub struct TargetFollowPath {
     paths: Vec<Vec<Direction>>,
@@ -53,18 +89,18 @@ pub struct TargetFollowPath {
 
 impl TargetFollowPath {
     pub fn new(n: usize, map: &Map, starting_points: Vec<Point>, targets: &mut Vec<Target>,
-               generate: bool, len: i32) -> Self {
+               generate: bool, len: PathLength, same_dir: f64) -> Self {
         let mut res = TargetFollowPath {
             paths: Vec::new(),
             path_idx: vec![0; n],
             starting_points: Vec::new(),
         };
-        res.create(n, map, starting_points, targets, generate, len);
+        res.create(n, map, starting_points, targets, generate, len, same_dir);
         res
     }
 
     fn create(&mut self, n: usize, map: &Map, starting_points: Vec<Point>,
-              targets: &mut Vec<Target>, generate: bool, len: i32) {
+              targets: &mut [Target], generate: bool, len: PathLength, same_dir: f64) {
         self.paths = vec![Vec::new(); n];
         let mut blocked = vec![vec![HashSet::new(); map.height]; map.width];
         assert!(starting_points.len() == n);
@@ -75,7 +111,8 @@ impl TargetFollowPath {
         }
         if !generate { return; }
         for (i, target) in targets.iter_mut().enumerate() {
-            self.generate_path(i, len, map, self.starting_points[i], target.timer, 5.0, &mut blocked);
+            let len = len.sample(&mut rand::thread_rng());
+            self.generate_path(i, len, map, self.starting_points[i], target.timer, same_dir, &mut blocked);
             self.generate_path_target(map, i, self.starting_points[i], target);
         }
         // println!("{:?}", targets);
@@ -87,57 +124,60 @@ impl TargetFollowPath {
         blocked[x][y].contains(&time)
     }
 
-    fn generate_path(&mut self, idx: usize, mut len: i32, map: &Map, start_position: Point, timer: i32,
-                     same_dir: f64, blocked: &mut Vec<Vec<HashSet<usize>>>) {
-        if len == -1 {
-            len = 10; // randomize later
-            todo!();
+    // direction from probs where the previous move (last) weighs same_dir times more
+    fn sample_direction(dirs: &[Direction], probs: &[f64], last: Option<usize>, same_dir: f64) -> Direction {
+        let mut pr_now = probs.to_vec();
+        if let Some(last) = last {
+            let sm = 1.0 + pr_now[last]*(same_dir-1.0);
+            pr_now[last] *= same_dir;
+            for pr in pr_now.iter_mut() {
+                *pr /= sm;
+            }
+        }
+        let rng = rand::random::<f64>();
+        let mut sm = 0.0;
+        for i in 0..pr_now.len() {
+            sm += pr_now[i];
+            if sm >= rng {
+                return dirs[i];
+            }
         }
+        dirs[dirs.len()-1]
+    }
 
+    fn generate_path(&mut self, idx: usize, len: usize, map: &Map, start_position: Point, timer: i32,
+                     same_dir: f64, blocked: &mut Vec<Vec<HashSet<usize>>>) {
         let dirs = Vec::from([Direction::North, Direction::East,
             Direction::South, Direction::West, Direction::None]);
         let probs = vec![0.225, 0.225, 0.225, 0.225, 0.1];
         let mut position = start_position.clone();
         let mut time_now = timer;
-        let last = -1;
+        // previous move, rests do not change it
+        let mut last = None;
         for i in 0..len {
             let mut iter = 0;
-            let mut dir = dirs.choose(&mut rand::thread_rng()).unwrap();
+            let mut dir = TargetFollowPath::sample_direction(&dirs, &probs, last, same_dir);
             if time_now == 0 { iter = std::i32::MAX; }
-            while iter < 20 && (!map.valid_point(&go_direction(position, *dir))
-                || self.is_blocked(&go_direction(position, *dir), (i+1) as usize, blocked)) {
-
-                // dir = dirs.choose(&mut rand::thread_rng()).unwrap();
-                let mut pr_now = probs.clone();
-                if last != -1 {
-                    pr_now[last as usize] *= same_dir;
-                    let sm = 0.8 + 0.2*same_dir;
-                    for i in 0..pr_now.len() {
-                        pr_now[i] /= sm;
-                    }
-                }
-                let mut rng = rand::random::<f64>();
-                let mut sm = 0.0;
-                for i in 0..pr_now.len() {
-                    sm += pr_now[i];
-                    if sm >= rng {
-                        dir = &dirs[i];
-                        break;
-                    }
-                }
+            while iter < 20 && (!map.valid_point(&go_direction(position, dir))
+                || self.is_blocked(&go_direction(position, dir), i+1, blocked)) {
+
+                dir = TargetFollowPath::sample_direction(&dirs, &probs, last, same_dir);
                 iter += 1;
             }
-            if iter >= 20 { dir = &Direction::None; }
+            if iter >= 20 { dir = Direction::None; }
             // TODO: debug?
-            if !map.valid_point(&go_direction(position, *dir)) {
-                println!("{:?}, {:?}, {:?}", position, *dir, iter);
+            if !map.valid_point(&go_direction(position, dir)) {
+                println!("{:?}, {:?}, {:?}", position, dir, iter);
                 panic!();
             }
-            if *dir == Direction::None { time_now = timer; }
-            else { time_now -= 1; }
-            self.paths[idx].push(*dir);
-            position = go_direction(position, *dir);
-            blocked[position.x][position.y].insert((i+1) as usize);
+            if dir == Direction::None { time_now = timer; }
+            else {
+                time_now -= 1;
+                last = dirs.iter().position(|x| *x == dir);
+            }
+            self.paths[idx].push(dir);
+            position = go_direction(position, dir);
+            blocked[position.x][position.y].insert(i+1);
         }
     }
 
@@ -171,18 +211,69 @@ impl TargetFollowPath {
         target.idx = idx;
     }
 
+    // cells of target idx over time, it stays in place after its path ends
+    fn cells(&self, idx: usize) -> Vec<Point> {
+        let mut res = vec![self.starting_points[idx]];
+        for dir in self.paths[idx].iter() {
+            res.push(go_direction(res[res.len()-1], *dir));
+        }
+        res
+    }
+
+    // replaces the path of target idx (starting at its current position and timer), with
+    // account_for_d the rests required by the timer are inserted before moves, otherwise such a
+    // path is an error; the timer works as in Runner (a move decrements it, a rest resets it to
+    // d_time), moves into walls and cells taken by or swapped with other targets are errors
     pub fn set_path(&mut self, idx: usize, path: &Vec<Direction>, map: &Map,
-                    target: &mut Target, account_for_d: bool) {
-        if account_for_d { unimplemented!(); }
-        let mut cells = Vec::new();
-        let mut now = target.position.clone();
-        cells.push(now);
+                    target: &mut Target, d_time: i32, account_for_d: bool) -> Result<(), String> {
+        let mut dirs = Vec::new();
+        let mut timer = target.timer;
         for dir in path.iter() {
-            now = go_direction(now, *dir);
-            cells.push(now);
+            if timer == 0 && *dir != Direction::None {
+                if !account_for_d {
+                    return Err(format!("move {:?} at time {} without the rest", dir, dirs.len()));
+                }
+                if d_time == 0 {
+                    return Err("target with timer 0 can not move".to_string());
+                }
+                dirs.push(Direction::None);
+                timer = d_time;
+            }
+            dirs.push(*dir);
+            if *dir == Direction::None { timer = d_time; }
+            else { timer -= 1; }
+        }
+
+        let mut cells = vec![target.position];
+        for (time, dir) in dirs.iter().enumerate() {
+            let now = cells[time];
+            if !map.valid_direction(now, *dir) || !map.valid_point(&go_direction(now, *dir)) {
+                return Err(format!("move {:?} from {:?} at time {} hits a wall", dir, now, time));
+            }
+            cells.push(go_direction(now, *dir));
         }
+
+        for other in 0..self.paths.len() {
+            if other == idx { continue; }
+            let other_cells = self.cells(other);
+            let at = |cells: &Vec<Point>, time: usize| cells[cmp::min(time, cells.len()-1)];
+            for time in 0..cmp::max(cells.len(), other_cells.len()) {
+                let p = at(&cells, time);
+                if p == at(&other_cells, time) {
+                    return Err(format!("collides with target {} at {:?} at time {}", other, p, time));
+                }
+                if time > 0 && p == at(&other_cells, time-1) && at(&cells, time-1) == at(&other_cells, time) {
+                    return Err(format!("swaps with target {} at {:?} at time {}", other, p, time-1));
+                }
+            }
+        }
+
+        self.starting_points[idx] = target.position;
+        self.paths[idx] = dirs;
+        self.path_idx[idx] = 0;
         target.path = Some(cells);
-        self.paths[idx] = path.clone();
+        target.idx = idx;
+        Ok(())
     }
 }
 
@@ -252,3 +343,669 @@ impl TargetStrategy for MaximizeMinDist {
 
     fn flush(&mut self) {}
 }
+
+// weight of the distance to the closest other target in the score of an Evader move,
+// the distance counts up to SPREAD_RANGE, so spreading out is worth at most 1.5 turns
+// and never outweighs escaping a capture (captures score at least 2 less)
+pub const SPREAD: f64 = 0.5;
+const SPREAD_RANGE: usize = 3;
+
+// depth limited minimax against every agent that can still catch the target (one agent
+// at a time, the target moves first) plus spread times the distance to the other targets,
+// targets pick in order of danger and claim their next cell so they do not collide,
+// ties go to the move furthest from the agents
+#[allow(dead_code)]
+pub struct Evader {
+    pub depth: usize,
+    pub d_time: i32,
+    pub spread: f64,
+}
+
+#[allow(dead_code)]
+impl Evader {
+    pub fn new(depth: usize, d_time: i32) -> Self {
+        Evader { depth, d_time, spread: SPREAD }
+    }
+
+    // cells reachable in one turn, staying included
+    fn moves(map: &Map, point: Point) -> Vec<(Direction, Point)> {
+        [Direction::North, Direction::East, Direction::South, Direction::West, Direction::None].iter()
+            .filter(|dir| map.valid_direction(point, **dir))
+            .map(|dir| (*dir, go_direction(point, *dir)))
+            .filter(|(_, p)| map.valid_point(p))
+            .collect()
+    }
+
+    // target moves, then the agent answers, depth turns in total (at least one);
+    // capture with depth turns left scores -depth
+    fn value(&self, map: &Map, target: Point, timer: i32, agent: Point, depth: usize) -> i64 {
+        if depth == 0 {
+            return map.dist_point(&agent, &target) as i64;
+        }
+        let mut best = i64::MIN;
+        for (dir, next) in Evader::moves(map, target) {
+            // must rest when the timer runs out
+            if timer == 0 && dir != Direction::None { continue; }
+            let timer_next = if dir == Direction::None { self.d_time } else { timer-1 };
+            best = cmp::max(best, self.answer(map, next, timer_next, agent, depth));
+        }
+        best
+    }
+
+    // best agent answer once the target stands at target
+    fn answer(&self, map: &Map, target: Point, timer: i32, agent: Point, depth: usize) -> i64 {
+        Evader::moves(map, agent).iter()
+            .map(|(_, chase)| {
+                if *chase == target { -(depth as i64) }
+                else { self.value(map, target, timer, *chase, depth-1) }
+            })
+            .min()
+            .unwrap()
+    }
+}
+
+impl TargetStrategy for Evader {
+    fn pick(&mut self, map: &Map, agents: &Vec<Agent>, targets: &Vec<Target>) -> Vec<Direction> {
+        let mut res = vec![Direction::None; targets.len()];
+
+        // agents that catch the target when standing on it
+        let chasers = |target: &Target| agents.iter()
+            .filter(|x| x.active && (x.targets == -1 || x.targets == target.idx as i32))
+            .map(|x| x.position)
+            .collect::<Vec<_>>();
+        let danger = |target: &Target| chasers(target).iter()
+            .map(|x| map.dist_point(x, &target.position))
+            .min()
+            .unwrap_or(usize::MAX);
+
+        let mut order = (0..targets.len()).collect::<Vec<_>>();
+        order.sort_by_key(|idx| danger(&targets[*idx]));
+
+        // next cells of targets that already picked, current cells of the others
+        let mut claimed = targets.iter().map(|x| x.position).collect::<Vec<_>>();
+        for idx in order {
+            let target = &targets[idx];
+            let chasers = chasers(target);
+            let mut best = None;
+            for (dir, next) in Evader::moves(map, target.position) {
+                if target.timer == 0 && dir != Direction::None { continue; }
+                if dir != Direction::None && claimed.contains(&next) { continue; }
+                let timer = if dir == Direction::None { self.d_time } else { target.timer-1 };
+                // nobody can catch it, only spreading out matters
+                let value = chasers.iter()
+                    .map(|agent| self.answer(map, next, timer, *agent, self.depth.max(1)))
+                    .min()
+                    .unwrap_or(0);
+                // a doomed target still does not walk into an agent
+                let away = chasers.iter()
+                    .map(|agent| map.dist_point(agent, &next))
+                    .min()
+                    .unwrap_or(usize::MAX);
+                let spread = claimed.iter().enumerate()
+                    .filter(|(other, _)| *other != idx)
+                    .map(|(_, p)| map.dist_point(p, &next))
+                    .min()
+                    .unwrap_or(usize::MAX);
+                let score = value as f64 + self.spread*cmp::min(spread, SPREAD_RANGE) as f64;
+                if best.is_none_or(|(s, a, _)| (score, away) > (s, a)) {
+                    best = Some((score, away, dir));
+                }
+            }
+            // staying is always allowed, nobody claims a cell another target still stands on
+            res[idx] = best.unwrap().2;
+            claimed[idx] = go_direction(target.position, res[idx]);
+        }
+
+        res
+    }
+
+    fn flush(&mut self) {}
+}
+
+// latest capture for a single target from the exact game solution (small maps only)
+#[allow(dead_code)]
+pub struct OptimalTarget {
+    solver: Rc<GameSolver>,
+}
+
+#[allow(dead_code)]
+impl OptimalTarget {
+    pub fn new(solver: Rc<GameSolver>) -> Self {
+        OptimalTarget { solver }
+    }
+}
+
+impl TargetStrategy for OptimalTarget {
+    fn pick(&mut self, map: &Map, agents: &Vec<Agent>, targets: &Vec<Target>) -> Vec<Direction> {
+        let positions = agents.iter().map(|x| x.position).collect::<Vec<_>>();
+        targets.iter()
+            .map(|x| self.solver.target_move(map, &positions, x.position, x.timer))
+            .collect()
+    }
+
+    fn flush(&mut self) {}
+}
+
+// every target walks a cycle of waypoints along shortest paths and rests when its timer runs
+// out, the motion is deterministic so Target::path is precomputed for foresight agents
+#[allow(dead_code)]
+#[derive(Clone)]
+pub struct Patrol {
+    waypoints: Vec<Vec<Point>>,
+    next: Vec<usize>,
+    d_time: i32,
+}
+
+#[allow(dead_code)]
+impl Patrol {
+    // waypoints[idx] for the target with idx, paths cover horizon turns
+    pub fn new(map: &Map, waypoints: Vec<Vec<Point>>, targets: &mut [Target], d_time: i32,
+               horizon: usize) -> Result<Self, String> {
+        if waypoints.len() != targets.len() {
+            return Err(format!("{} waypoint lists for {} targets", waypoints.len(), targets.len()));
+        }
+        for (idx, target) in targets.iter_mut().enumerate() {
+            for p in waypoints[idx].iter() {
+                if map.dist_point(&target.position, p) == usize::MAX {
+                    return Err(format!("target {} can not reach waypoint {:?}", idx, p));
+                }
+            }
+            target.idx = idx;
+        }
+
+        let res = Patrol {
+            next: vec![0; waypoints.len()],
+            waypoints,
+            d_time,
+        };
+        for target in targets.iter_mut() {
+            let mut position = target.position;
+            let mut timer = target.timer;
+            let mut next = 0;
+            let mut path = vec![position];
+            for _ in 0..horizon {
+                let dir = res.step(map, target.idx, position, timer, &mut next);
+                position = go_direction(position, dir);
+                // same as Runner
+                if dir == Direction::None { timer = d_time; }
+                else { timer -= 1; }
+                path.push(position);
+            }
+            target.path = Some(path);
+        }
+        Ok(res)
+    }
+
+    fn step(&self, map: &Map, idx: usize, position: Point, timer: i32, next: &mut usize) -> Direction {
+        let points = &self.waypoints[idx];
+        if points.is_empty() { return Direction::None; }
+        // reached waypoints are skipped
+        for _ in 0..points.len() {
+            if position != points[*next] { break; }
+            *next = (*next+1) % points.len();
+        }
+        if timer == 0 || position == points[*next] { return Direction::None; }
+        map.get_direction(&position, &points[*next])
+    }
+}
+
+impl TargetStrategy for Patrol {
+    fn pick(&mut self, map: &Map, _agents: &Vec<Agent>, targets: &Vec<Target>) -> Vec<Direction> {
+        let mut res = Vec::new();
+        for target in targets.iter() {
+            let mut next = self.next[target.idx];
+            res.push(self.step(map, target.idx, target.position, target.timer, &mut next));
+            self.next[target.idx] = next;
+        }
+        res
+    }
+
+    fn flush(&mut self) {
+        for x in &mut self.next {
+            *x = 0;
+        }
+    }
+}
+
+// what decides the next move of a target with a known motion model
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct MotionState {
+    pub position: Point,
+    pub timer: i32,
+    pub heading: Direction, // previous move, None at the start
+}
+
+// targets whose motion is a known distribution over their next moves
+pub trait TransitionModel {
+    // next states with their probabilities (summing to 1)
+    fn transition(&self, map: &Map, state: &MotionState) -> Vec<(MotionState, f64)>;
+
+    // belief after one more turn
+    fn propagate(&self, map: &Map, belief: &HashMap<MotionState, f64>) -> HashMap<MotionState, f64> {
+        let mut res = HashMap::new();
+        for (state, pr) in belief.iter() {
+            for (next, step) in self.transition(map, state) {
+                *res.entry(next).or_insert(0.0) += pr*step;
+            }
+        }
+        res
+    }
+
+    // position distributions of a target 0..turns turns after it was in state
+    fn distribution(&self, map: &Map, state: MotionState, turns: usize) -> Vec<HashMap<Point, f64>> {
+        let mut belief = HashMap::from([(state, 1.0)]);
+        let mut res = Vec::new();
+        for time in 0..turns {
+            if time > 0 { belief = self.propagate(map, &belief); }
+            let mut dist = HashMap::new();
+            for (state, pr) in belief.iter() {
+                *dist.entry(state.position).or_insert(0.0) += pr;
+            }
+            res.push(dist);
+        }
+        res
+    }
+}
+
+// weights of [North, East, South, West, None], moves into walls are dropped and the rest
+// normalised, a target with timer 0 always rests
+#[allow(dead_code)]
+#[derive(Clone, Debug)]
+pub enum MarkovModel {
+    Uniform,
+    // by the previous move, in the same order
+    PerHeading([[f64; 5]; 5]),
+    // by the current cell, default for cells missing in the map
+    PerCell(HashMap<Point, [f64; 5]>, [f64; 5]),
+}
+
+const MARKOV_DIRS: [Direction; 5] = [Direction::North, Direction::East, Direction::South,
+                                      Direction::West, Direction::None];
+
+// samples every move from a MarkovModel
+pub struct MarkovTarget {
+    pub model: MarkovModel,
+    pub d_time: i32,
+    headings: HashMap<usize, Direction>,
+}
+
+#[allow(dead_code)]
+impl MarkovTarget {
+    pub fn new(model: MarkovModel, d_time: i32) -> Self {
+        MarkovTarget { model, d_time, headings: HashMap::new() }
+    }
+
+    pub fn state(&self, target: &Target) -> MotionState {
+        MotionState {
+            position: target.position,
+            timer: target.timer,
+            heading: *self.headings.get(&target.idx).unwrap_or(&Direction::None),
+        }
+    }
+
+    fn weights(&self, state: &MotionState) -> [f64; 5] {
+        match &self.model {
+            MarkovModel::Uniform => [1.0; 5],
+            MarkovModel::PerHeading(weights) => weights[MARKOV_DIRS.iter().position(|x| *x == state.heading).unwrap()],
+            MarkovModel::PerCell(weights, default) => *weights.get(&state.position).unwrap_or(default),
+        }
+    }
+}
+
+impl TransitionModel for MarkovTarget {
+    fn transition(&self, map: &Map, state: &MotionState) -> Vec<(MotionState, f64)> {
+        let rest = MotionState { position: state.position, timer: self.d_time, heading: Direction::None };
+        if state.timer == 0 {
+            return vec![(rest, 1.0)];
+        }
+        let weights = self.weights(state);
+        let mut res = Vec::new();
+        for (dir, weight) in MARKOV_DIRS.iter().zip(weights.iter()) {
+            if *weight <= 0.0 { continue; }
+            if !map.valid_direction(state.position, *dir) || !map.valid_point(&go_direction(state.position, *dir)) {
+                continue;
+            }
+            let timer = if *dir == Direction::None { self.d_time } else { state.timer-1 };
+            res.push((MotionState { position: go_direction(state.position, *dir), timer, heading: *dir }, *weight));
+        }
+        // nothing allowed, stay
+        if res.is_empty() {
+            return vec![(rest, 1.0)];
+        }
+        let sum = res.iter().map(|x| x.1).sum::<f64>();
+        for x in res.iter_mut() {
+            x.1 /= sum;
+        }
+        res
+    }
+}
+
+impl TargetStrategy for MarkovTarget {
+    fn pick(&mut self, map: &Map, _agents: &Vec<Agent>, targets: &Vec<Target>) -> Vec<Direction> {
+        let mut res = Vec::new();
+        for target in targets.iter() {
+            let next = self.transition(map, &self.state(target));
+            let rng = rand::random::<f64>();
+            let mut sm = 0.0;
+            let mut dir = next[next.len()-1].0.heading;
+            for (state, pr) in next.iter() {
+                sm += pr;
+                if sm >= rng {
+                    dir = state.heading;
+                    break;
+                }
+            }
+            self.headings.insert(target.idx, dir);
+            res.push(dir);
+        }
+        res
+    }
+
+    fn flush(&mut self) {
+        self.headings.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use crate::map::*;
+    use crate::target_strategies::*;
+    use crate::agent_strategies::NoCollisionSingle;
+    use crate::runner::Runner;
+
+    #[test]
+    fn evader_rests_when_timer_runs_out() {
+        let map = Map::new("resources/maps/box.map");
+        let agents = agents_from(&vec![Point{x: 4, y: 4}]);
+        let mut targets = targets_from(&vec![Point{x: 5, y: 4}], 0);
+        let mut strat = Evader::new(2, 3);
+        assert_eq!(strat.pick(&map, &agents, &targets), vec![Direction::None]);
+
+        targets[0].timer = 3;
+        let dir = strat.pick(&map, &agents, &targets)[0];
+        assert_eq!(map.dist_point(&agents[0].position, &go_direction(targets[0].position, dir)), 2);
+    }
+
+    #[test]
+    fn evader_ignores_agents_of_other_targets() {
+        let map = Map::new("resources/maps/box.map");
+        let mut agents = agents_from(&vec![Point{x: 4, y: 4}]);
+        agents[0].targets = 1;
+        let targets = targets_from(&vec![Point{x: 5, y: 4}], 3);
+        let mut strat = Evader::new(2, 3);
+        // nobody can catch the target, every move ties and the first one wins
+        assert_eq!(strat.pick(&map, &agents, &targets), vec![Direction::North]);
+    }
+
+    #[test]
+    fn evaders_do_not_collide() {
+        let map = Map::new("resources/maps/box.map");
+        let d_time = 2;
+        for _ in 0..10 {
+            // distinct cells for everybody
+            let mut agents = agents_random(&map, 6);
+            let mut targets = targets_from(&agents.split_off(2).iter().map(|x| x.position).collect(), d_time);
+            for (idx, target) in targets.iter_mut().enumerate() { target.idx = idx; }
+            let mut strat = Evader::new(2, d_time);
+            for _ in 0..30 {
+                let dirs = strat.pick(&map, &agents, &targets);
+                for (target, dir) in targets.iter_mut().zip(dirs.iter()) {
+                    if target.timer == 0 { assert_eq!(*dir, Direction::None); }
+                    target.position = go_direction(target.position, *dir);
+                    assert!(map.valid_point(&target.position));
+                    if *dir == Direction::None { target.timer = d_time; }
+                    else { target.timer -= 1; }
+                }
+                let positions = targets.iter().map(|x| x.position).collect::<HashSet<_>>();
+                assert_eq!(positions.len(), targets.len());
+                // agents stand still, so nobody walks into them
+                assert!(agents.iter().all(|a| !positions.contains(&a.position)));
+            }
+        }
+    }
+
+    #[test]
+    fn evaders_spread_out() {
+        let map = Map::new("resources/maps/box.map");
+        let mut targets = targets_from(&vec![Point{x: 4, y: 4}, Point{x: 5, y: 4}, Point{x: 4, y: 5}], 10);
+        for (idx, target) in targets.iter_mut().enumerate() { target.idx = idx; }
+        let mut strat = Evader::new(2, 10);
+        for _ in 0..3 {
+            let dirs = strat.pick(&map, &Vec::new(), &targets);
+            for (target, dir) in targets.iter_mut().zip(dirs.iter()) {
+                target.position = go_direction(target.position, *dir);
+            }
+        }
+        for (idx, target) in targets.iter().enumerate() {
+            let closest = targets.iter().enumerate()
+                .filter(|(other, _)| *other != idx)
+                .map(|(_, x)| map.dist_point(&x.position, &target.position))
+                .min().unwrap();
+            assert!(closest >= SPREAD_RANGE, "{:?}", targets);
+        }
+
+        // without the spread term nothing moves them apart
+        let mut strat = Evader::new(2, 10);
+        strat.spread = 0.0;
+        let targets = targets_from(&vec![Point{x: 4, y: 4}, Point{x: 5, y: 4}], 10);
+        assert_eq!(strat.pick(&map, &Vec::new(), &targets), vec![Direction::North, Direction::North]);
+    }
+
+    #[test]
+    fn path_lengths() {
+        let mut rng = rand::thread_rng();
+        assert_eq!(PathLength::Fixed(7).sample(&mut rng), 7);
+        for _ in 0..100 {
+            let len = PathLength::Uniform(3, 5).sample(&mut rng);
+            assert!((3..=5).contains(&len));
+        }
+        assert_eq!(PathLength::Geometric(1.0).sample(&mut rng), 0);
+        let mean = (0..2000).map(|_| PathLength::Geometric(0.25).sample(&mut rng)).sum::<usize>() as f64 / 2000.0;
+        assert!((mean-3.0).abs() < 0.5, "{}", mean);
+
+        let map = Map::new("resources/maps/box.map");
+        let mut targets = targets_from(&vec![Point{x: 1, y: 1}, Point{x: 5, y: 5}], 3);
+        let strat = TargetFollowPath::new(2, &map, targets.iter().map(|x| x.position).collect(),
+                                          &mut targets, true, PathLength::Uniform(4, 6), SAME_DIR);
+        for path in strat.paths.iter() {
+            assert!((4..=6).contains(&path.len()));
+        }
+    }
+
+    // share of moves that repeat the previous move
+    fn repeated(same_dir: f64) -> f64 {
+        let map = Map::new("resources/maps/box.map");
+        let (mut same, mut all) = (0, 0);
+        for _ in 0..5 {
+            let mut targets = targets_from(&vec![Point{x: 4, y: 4}], i32::MAX);
+            let strat = TargetFollowPath::new(1, &map, vec![targets[0].position], &mut targets, true,
+                                              PathLength::Fixed(200), same_dir);
+            let moves = strat.paths[0].iter().filter(|x| **x != Direction::None).collect::<Vec<_>>();
+            same += moves.windows(2).filter(|x| x[0] == x[1]).count();
+            all += moves.len()-1;
+        }
+        same as f64 / all as f64
+    }
+
+    #[test]
+    fn same_direction_bias() {
+        // walls turn the target around, so even a strong bias is not always followed
+        assert!(repeated(50.0) > 0.6);
+        assert!(repeated(1.0) < 0.45);
+    }
+
+    #[test]
+    fn set_path_inserts_rests() {
+        let map = Map::new("resources/maps/box.map");
+        let d_time = 2;
+        let mut targets = targets_from(&vec![Point{x: 1, y: 1}, Point{x: 1, y: 3}], d_time);
+        let mut strat = TargetFollowPath::new(2, &map, targets.iter().map(|x| x.position).collect(),
+                                              &mut targets, false, PathLength::Fixed(0), SAME_DIR);
+        let path = vec![Direction::East; 4];
+        assert!(strat.set_path(0, &path, &map, &mut targets[0], d_time, false).is_err());
+        strat.set_path(0, &path, &map, &mut targets[0], d_time, true).unwrap();
+        assert_eq!(strat.paths[0], vec![Direction::East, Direction::East, Direction::None,
+                                        Direction::East, Direction::East]);
+        assert_eq!(targets[0].at_time(5), Point{x: 5, y: 1});
+
+        // replay as Runner does
+        let mut target = targets[0].clone();
+        for time in 0..8 {
+            assert_eq!(target.position, targets[0].at_time(time));
+            let dir = strat.pick(&map, &Vec::new(), &vec![target.clone()])[0];
+            target.position = go_direction(target.position, dir);
+            if dir == Direction::None { target.timer = d_time; }
+            else { target.timer -= 1; }
+        }
+    }
+
+    #[test]
+    fn set_path_errors() {
+        let map = Map::new("resources/maps/box.map");
+        let mut targets = targets_from(&vec![Point{x: 1, y: 1}, Point{x: 1, y: 3}], 5);
+        let mut strat = TargetFollowPath::new(2, &map, targets.iter().map(|x| x.position).collect(),
+                                              &mut targets, false, PathLength::Fixed(0), SAME_DIR);
+        assert!(strat.set_path(0, &vec![Direction::West], &map, &mut targets[0], 5, true).is_err());
+        // second target stands still at (1, 3)
+        let up = vec![Direction::North, Direction::North];
+        assert!(strat.set_path(0, &up, &map, &mut targets[0], 5, true).is_err());
+        strat.set_path(1, &vec![Direction::East], &map, &mut targets[1], 5, true).unwrap();
+        strat.set_path(0, &up, &map, &mut targets[0], 5, true).unwrap();
+
+        let mut stuck = targets_from(&vec![Point{x: 4, y: 4}], 0);
+        let mut strat = TargetFollowPath::new(1, &map, vec![stuck[0].position], &mut stuck, false,
+                                              PathLength::Fixed(0), SAME_DIR);
+        assert!(strat.set_path(0, &vec![Direction::East], &map, &mut stuck[0], 0, true).is_err());
+
+        // neighbours can not swap cells
+        let mut targets = targets_from(&vec![Point{x: 1, y: 1}, Point{x: 2, y: 1}], 5);
+        let mut strat = TargetFollowPath::new(2, &map, targets.iter().map(|x| x.position).collect(),
+                                              &mut targets, false, PathLength::Fixed(0), SAME_DIR);
+        strat.set_path(0, &vec![Direction::North], &map, &mut targets[0], 5, true).unwrap();
+        strat.set_path(1, &vec![Direction::West], &map, &mut targets[1], 5, true).unwrap();
+        assert!(strat.set_path(0, &vec![Direction::East], &map, &mut targets[0], 5, true).is_err());
+    }
+
+    #[test]
+    fn set_path_starts_from_current_timer() {
+        let map = Map::new("resources/maps/box.map");
+        let mut targets = targets_from(&vec![Point{x: 1, y: 1}], 3);
+        targets[0].timer = 1;
+        let mut strat = TargetFollowPath::new(1, &map, vec![targets[0].position], &mut targets, false,
+                                              PathLength::Fixed(0), SAME_DIR);
+        strat.set_path(0, &vec![Direction::East; 4], &map, &mut targets[0], 3, true).unwrap();
+        assert_eq!(strat.paths[0], vec![Direction::East, Direction::None, Direction::East,
+                                        Direction::East, Direction::East]);
+    }
+
+    #[test]
+    fn patrol_follows_precomputed_path() {
+        let map = Map::new("resources/maps/box.map");
+        let d_time = 3;
+        let mut targets = targets_from(&vec![Point{x: 1, y: 1}, Point{x: 8, y: 8}], d_time);
+        let corners = vec![Point{x: 1, y: 1}, Point{x: 8, y: 1}, Point{x: 8, y: 8}, Point{x: 1, y: 8}];
+        let mut strat = Patrol::new(&map, vec![corners.clone(), vec![Point{x: 8, y: 8}]], &mut targets,
+                                    d_time, 100).unwrap();
+
+        // replay as Runner does
+        let mut now = targets.clone();
+        let mut visited = Vec::new();
+        for time in 0..100 {
+            for target in now.iter() {
+                assert_eq!(target.position, targets[target.idx].at_time(time));
+            }
+            if corners.contains(&now[0].position) && visited.last() != Some(&now[0].position) {
+                visited.push(now[0].position);
+            }
+            let dirs = strat.pick(&map, &Vec::new(), &now);
+            for (target, dir) in now.iter_mut().zip(dirs.iter()) {
+                if target.timer == 0 { assert_eq!(*dir, Direction::None); }
+                target.position = go_direction(target.position, *dir);
+                if *dir == Direction::None { target.timer = d_time; }
+                else { target.timer -= 1; }
+            }
+        }
+        // two rounds (28 moves and 9 rests each) in order
+        assert_eq!(visited[..9], [corners.clone(), corners.clone(), vec![corners[0]]].concat());
+        assert_eq!(now[1].position, Point{x: 8, y: 8});
+
+        strat.flush();
+        assert!(Patrol::new(&map, vec![corners.clone()], &mut targets, d_time, 10).is_err());
+        assert!(Patrol::new(&map, vec![vec![Point{x: 0, y: 0}], vec![]], &mut targets, d_time, 10).is_err());
+    }
+
+    #[test]
+    fn foresight_agent_catches_patrol() {
+        let map = Map::new("resources/maps/tunnel.map");
+        let d_time = 4;
+        let mut targets = targets_from(&vec![Point{x: 20, y: 2}], d_time);
+        let strat = Patrol::new(&map, vec![vec![Point{x: 27, y: 1}, Point{x: 17, y: 3}]], &mut targets,
+                                d_time, 200).unwrap();
+        let agents = agents_from(&vec![Point{x: 1, y: 1}]);
+        let mut single = NoCollisionSingle::new();
+        single.prep(&map, &agents[0], &targets[0]);
+
+        let mut runner = Runner { map: &map, agents, targets, d_time, observation: None };
+        let mut target_strat: Box<dyn TargetStrategy> = Box::new(strat);
+        let expected = single.expected_time;
+        let turns = runner.run(Box::new(single), &mut target_strat, false, false, false, false, "", 1000);
+        assert_eq!(turns, expected);
+    }
+
+    #[test]
+    fn markov_transitions() {
+        let map = Map::new("resources/maps/box.map");
+        let model = MarkovTarget::new(MarkovModel::Uniform, 3);
+        let corner = MotionState { position: Point{x: 1, y: 1}, timer: 2, heading: Direction::None };
+        let next = model.transition(&map, &corner);
+        // two walls around the corner
+        assert_eq!(next.len(), 3);
+        assert!(next.iter().all(|x| (x.1-1.0/3.0).abs() < 1e-9));
+        assert!(next.iter().any(|x| x.0 == MotionState { position: Point{x: 1, y: 1}, timer: 3, heading: Direction::None }));
+        assert!(next.iter().any(|x| x.0 == MotionState { position: Point{x: 2, y: 1}, timer: 1, heading: Direction::East }));
+
+        let tired = MotionState { timer: 0, ..corner };
+        assert_eq!(model.transition(&map, &tired), vec![(MotionState { timer: 3, ..corner }, 1.0)]);
+
+        // keeps going the same way
+        let mut weights = [[0.0; 5]; 5];
+        for (i, row) in weights.iter_mut().enumerate() { row[i] = 1.0; }
+        weights[4] = [0.0, 1.0, 0.0, 0.0, 0.0];
+        let model = MarkovTarget::new(MarkovModel::PerHeading(weights), 10);
+        let dist = model.distribution(&map, MotionState { position: Point{x: 1, y: 4}, timer: 10, heading: Direction::None }, 6);
+        assert_eq!(dist[5], HashMap::from([(Point{x: 6, y: 4}, 1.0)]));
+        // walks into the wall, then nothing is allowed and it stays
+        let dist = model.distribution(&map, MotionState { position: Point{x: 1, y: 4}, timer: 10, heading: Direction::None }, 10);
+        assert_eq!(dist[9], HashMap::from([(Point{x: 8, y: 4}, 1.0)]));
+    }
+
+    #[test]
+    fn markov_distribution_matches_sampling() {
+        let map = Map::new("resources/maps/box.map");
+        let d_time = 1;
+        let mut cell = HashMap::new();
+        cell.insert(Point{x: 4, y: 4}, [4.0, 1.0, 1.0, 1.0, 1.0]);
+        let mut strat = MarkovTarget::new(MarkovModel::PerCell(cell, [1.0, 2.0, 1.0, 1.0, 0.5]), d_time);
+        let start = targets_from(&vec![Point{x: 4, y: 4}], d_time);
+        let expected = strat.distribution(&map, strat.state(&start[0]), 4).pop().unwrap();
+        assert!((expected.values().sum::<f64>()-1.0).abs() < 1e-9);
+
+        let runs = 4000;
+        let mut seen: HashMap<Point, f64> = HashMap::new();
+        for _ in 0..runs {
+            strat.flush();
+            let mut targets = start.clone();
+            for _ in 0..3 {
+                let dir = strat.pick(&map, &Vec::new(), &targets)[0];
+                targets[0].position = go_direction(targets[0].position, dir);
+                if dir == Direction::None { targets[0].timer = d_time; }
+                else { targets[0].timer -= 1; }
+            }
+            *seen.entry(targets[0].position).or_insert(0.0) += 1.0/runs as f64;
+        }
+        for (p, pr) in seen.iter() {
+            assert!((expected.get(p).unwrap_or(&0.0)-pr).abs() < 0.04, "{:?} {} {:?}", p, pr, expected.get(p));
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+use crate::map::Point;
+
+// nodes of a time expanded grid graph, one turn goes
+// Cell(t) -> Edge(t) -> EdgeExit(t) -> Arrival(t+1) -> Cell(t+1)
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Node {
+    Source,
+    Sink,
+    // position at the start of turn time
+    Cell { time: usize, point: Point },
+    // entered at the end of turn time-1, before the vertex capacity
+    Arrival { time: usize, point: Point },
+    // move between a and b (in any direction) during turn time, a == b for waiting
+    Edge { time: usize, a: Point, b: Point },
+    // after the capacity of the move, so a and b cannot swap
+    EdgeExit { time: usize, a: Point, b: Point },
+    // one per target, collects whoever catches it
+    Collector(usize),
+}
+
+// bijective mapping between nodes and dense ids (in order of first use)
+#[derive(Clone)]
+pub struct TimeExpanded {
+    ids: HashMap<Node, i32>,
+    nodes: Vec<Node>,
+}
+
+impl TimeExpanded {
+    pub fn new() -> Self {
+        TimeExpanded {
+            ids: HashMap::new(),
+            nodes: Vec::new(),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = TimeExpanded::new();
+    }
+
+    #[allow(dead_code)]
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    // id of node, allocates a new one on first use
+    pub fn id(&mut self, node: Node) -> i32 {
+        if let Some(&id) = self.ids.get(&node) { return id; }
+        let id = self.nodes.len() as i32;
+        self.ids.insert(node, id);
+        self.nodes.push(node);
+        id
+    }
+
+    pub fn get(&self, node: &Node) -> Option<i32> {
+        self.ids.get(node).copied()
+    }
+
+    pub fn node(&self, id: i32) -> Option<Node> {
+        if id < 0 { return None; }
+        self.nodes.get(id as usize).copied()
+    }
+
+    // move from -> to during turn time, both directions of a pair share the gadget
+    // and only one unit passes it, cost is paid when entering the gadget
+    pub fn edge_gadget(&mut self, add_edge: &mut impl FnMut(i32, i32, i32, i64),
+                       time: usize, from: Point, to: Point, cost: i64) {
+        let (a, b) = if (from.x, from.y) <= (to.x, to.y) { (from, to) } else { (to, from) };
+        let cell = self.id(Node::Cell { time, point: from });
+        let edge = self.id(Node::Edge { time, a, b });
+        // first direction of the pair adds the capacity
+        let exit = match self.get(&Node::EdgeExit { time, a, b }) {
+            Some(exit) => exit,
+            None => {
+                let exit = self.id(Node::EdgeExit { time, a, b });
+                add_edge(edge, exit, 1, 0);
+                exit
+            },
+        };
+        let arrival = self.id(Node::Arrival { time: time+1, point: to });
+        add_edge(cell, edge, 1, cost);
+        add_edge(exit, arrival, 1, 0);
+    }
+
+    // at most capacity units can stand on point at time
+    pub fn vertex_gadget(&mut self, add_edge: &mut impl FnMut(i32, i32, i32, i64),
+                         time: usize, point: Point, capacity: i32) {
+        let arrival = self.id(Node::Arrival { time, point });
+        let cell = self.id(Node::Cell { time, point });
+        add_edge(arrival, cell, capacity, 0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::map::Point;
+    use crate::time_expanded::*;
+
+    #[test]
+    fn ids_are_bijective() {
+        let mut graph = TimeExpanded::new();
+        let mut nodes = vec![Node::Source, Node::Sink, Node::Collector(0), Node::Collector(3)];
+        for time in 0..3 {
+            for x in 0..4 {
+                for y in 0..4 {
+                    let point = Point{x, y};
+                    nodes.push(Node::Cell { time, point });
+                    nodes.push(Node::Arrival { time, point });
+                    nodes.push(Node::Edge { time, a: point, b: Point{x: x+1, y} });
+                }
+            }
+        }
+
+        let ids = nodes.iter().map(|x| graph.id(*x)).collect::<Vec<_>>();
+        assert_eq!(ids, (0..nodes.len() as i32).collect::<Vec<_>>());
+        assert_eq!(graph.len(), nodes.len());
+        for (node, id) in nodes.iter().zip(ids.iter()) {
+            assert_eq!(graph.id(*node), *id);
+            assert_eq!(graph.get(node), Some(*id));
+            assert_eq!(graph.node(*id), Some(*node));
+        }
+        assert_eq!(graph.node(-1), None);
+        assert_eq!(graph.node(nodes.len() as i32), None);
+        assert_eq!(graph.get(&Node::Collector(1)), None);
+    }
+
+    #[test]
+    fn gadgets() {
+        let mut graph = TimeExpanded::new();
+        let mut edges = Vec::new();
+        let mut add_edge = |from, to, capacity, cost| edges.push((from, to, capacity, cost));
+        let p = Point{x: 1, y: 2};
+        let q = Point{x: 2, y: 2};
+        graph.edge_gadget(&mut add_edge, 4, p, q, 1);
+        graph.edge_gadget(&mut add_edge, 4, q, p, 1);
+        graph.vertex_gadget(&mut add_edge, 5, q, 1);
+
+        let id = |node| graph.get(&node).unwrap();
+        let edge = id(Node::Edge { time: 4, a: p, b: q });
+        let exit = id(Node::EdgeExit { time: 4, a: p, b: q });
+        assert_eq!(edges, vec![
+            (edge, exit, 1, 0),
+            (id(Node::Cell { time: 4, point: p }), edge, 1, 1),
+            (exit, id(Node::Arrival { time: 5, point: q }), 1, 0),
+            (id(Node::Cell { time: 4, point: q }), edge, 1, 1),
+            (exit, id(Node::Arrival { time: 5, point: p }), 1, 0),
+            (id(Node::Arrival { time: 5, point: q }), id(Node::Cell { time: 5, point: q }), 1, 0),
+        ]);
+    }
+}
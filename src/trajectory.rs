@@ -0,0 +1,155 @@
+use std::fs;
+use crate::map::*;
+use crate::target_strategies::{PathLength, TargetFollowPath, SAME_DIR};
+
+// scripted target motion, one target per line
+// # comment
+// target <x> <y> dirs <N|E|S|W|.>...          moves, '.' rests
+// target <x> <y> cells <x> <y>...             neighbouring cells visited one per turn
+// target <x> <y> waypoints <x> <y>...         shortest paths (Map::get_direction) between waypoints
+// rests required by the timer are inserted when loading (see TargetFollowPath::set_path)
+
+pub struct Trajectory {
+    pub start: Point,
+    pub path: Vec<Direction>,
+}
+
+fn direction(c: char) -> Option<Direction> {
+    match c {
+        'N' => Some(Direction::North),
+        'E' => Some(Direction::East),
+        'S' => Some(Direction::South),
+        'W' => Some(Direction::West),
+        '.' => Some(Direction::None),
+        _ => None,
+    }
+}
+
+pub fn parse_trajectories(text: &str, map: &Map) -> Result<Vec<Trajectory>, String> {
+    let mut res = Vec::new();
+    for (idx, line) in text.lines().enumerate() {
+        let err = |msg: &str| format!("line {}: {}: {}", idx+1, msg, line);
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        if words.is_empty() || words[0].starts_with('#') { continue; }
+        if words[0] != "target" || words.len() < 4 {
+            return Err(err("expected target <x> <y> <dirs|cells|waypoints> ..."));
+        }
+
+        let number = |word: &str| word.parse::<usize>().map_err(|_| err("expected a number"));
+        let mut points = Vec::new();
+        if words[3] != "dirs" {
+            if words.len() % 2 != 0 { return Err(err("odd number of coordinates")); }
+            for pair in words[4..].chunks(2) {
+                points.push(Point{x: number(pair[0])?, y: number(pair[1])?});
+            }
+        }
+        let start = Point{x: number(words[1])?, y: number(words[2])?};
+        for p in points.iter().chain([&start]) {
+            if !map.valid_point(p) { return Err(err(&format!("{:?} is not a free cell", p))); }
+        }
+
+        let mut path = Vec::new();
+        let mut now = start;
+        match words[3] {
+            "dirs" => {
+                for c in words[4..].concat().chars() {
+                    path.push(direction(c).ok_or_else(|| err(&format!("unknown direction {}", c)))?);
+                }
+            },
+            "cells" => {
+                for p in points {
+                    if now.x.abs_diff(p.x) + now.y.abs_diff(p.y) > 1 {
+                        return Err(err(&format!("{:?} is not next to {:?}", p, now)));
+                    }
+                    path.push(map.neighbor(&now, &p));
+                    now = p;
+                }
+            },
+            "waypoints" => {
+                for p in points {
+                    if map.dist_point(&now, &p) == usize::MAX {
+                        return Err(err(&format!("{:?} can not be reached from {:?}", p, now)));
+                    }
+                    while now != p {
+                        let dir = map.get_direction(&now, &p);
+                        path.push(dir);
+                        now = go_direction(now, dir);
+                    }
+                }
+            },
+            _ => return Err(err("expected dirs, cells or waypoints")),
+        }
+        res.push(Trajectory { start, path });
+    }
+    Ok(res)
+}
+
+// targets (with full timer d_time) following the trajectories
+pub fn load_trajectories(file_path: &str, map: &Map, d_time: i32)
+                         -> Result<(Vec<Target>, TargetFollowPath), String> {
+    let text = fs::read_to_string(file_path).map_err(|e| format!("{}: {}", file_path, e))?;
+    let trajectories = parse_trajectories(&text, map).map_err(|e| format!("{}: {}", file_path, e))?;
+
+    let starts = trajectories.iter().map(|x| x.start).collect::<Vec<_>>();
+    for (idx, p) in starts.iter().enumerate() {
+        if starts[..idx].contains(p) {
+            return Err(format!("{}: targets {} and {} start at {:?}", file_path,
+                               starts.iter().position(|x| x == p).unwrap(), idx, p));
+        }
+    }
+    let mut targets = targets_from(&starts, d_time);
+    let mut strat = TargetFollowPath::new(targets.len(), map, starts, &mut targets, false,
+                                          PathLength::Fixed(0), SAME_DIR);
+    for (idx, trajectory) in trajectories.iter().enumerate() {
+        strat.set_path(idx, &trajectory.path, map, &mut targets[idx], d_time, true)
+            .map_err(|e| format!("{}: target {}: {}", file_path, idx, e))?;
+    }
+    Ok((targets, strat))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::map::*;
+    use crate::trajectory::*;
+
+    #[test]
+    fn parse() {
+        let map = Map::new("resources/maps/box.map");
+        let text = "# three ways to the same place\n\
+                    target 1 1 dirs EE.N\n\
+                    target 1 1 cells 2 1 3 1 3 1 3 2\n\
+                    \n\
+                    target 1 1 waypoints 3 1 3 2\n";
+        let trajectories = parse_trajectories(text, &map).unwrap();
+        assert_eq!(trajectories.len(), 3);
+        for trajectory in trajectories.iter() {
+            assert_eq!(trajectory.start, Point{x: 1, y: 1});
+            assert_eq!(trajectory.path.iter().filter(|x| **x != Direction::None).count(), 3);
+        }
+        assert_eq!(trajectories[0].path, trajectories[1].path);
+        assert_eq!(trajectories[2].path, vec![Direction::East, Direction::East, Direction::North]);
+
+        assert!(parse_trajectories("agent 1 1 dirs N\n", &map).is_err());
+        assert!(parse_trajectories("target 1 1 dirs NX\n", &map).is_err());
+        assert!(parse_trajectories("target 0 0 dirs N\n", &map).is_err());
+        assert!(parse_trajectories("target 1 1 cells 3 1\n", &map).is_err());
+        assert!(parse_trajectories("target 1 1 waypoints 3\n", &map).is_err());
+        assert!(parse_trajectories("target 1 1 path 3 1\n", &map).is_err());
+    }
+
+    #[test]
+    fn stored_trajectories() {
+        let map = Map::new("resources/maps/tunnel.map");
+        let (targets, _) = load_trajectories("resources/trajectories/tunnel.txt", &map, 3).unwrap();
+        assert_eq!(targets.len(), 2);
+        for (idx, target) in targets.iter().enumerate() {
+            let path = target.path.as_ref().unwrap();
+            assert_eq!(target.idx, idx);
+            assert_eq!(path[0], target.position);
+            // rests were inserted, so no more than three moves in a row
+            assert!(path.windows(5).all(|x| x.windows(2).any(|y| y[0] == y[1])));
+        }
+
+        assert!(load_trajectories("resources/trajectories/missing.txt", &map, 3).is_err());
+    }
+}
@@ -75,6 +75,63 @@ impl Matcher for TurboMatching {
     fn get_matching(&mut self) -> &Vec<i32> {
         &self.mat
     }
+
+    fn add_edge(&mut self, u: usize, v: usize) {
+        let n = std::cmp::max(u, v)+1;
+        if n > self.g.len() {
+            self.g.resize(n, Vec::new());
+            self.vis.resize(n, false);
+            self.mat.resize(n, -1);
+        }
+        self.g[u].push(v);
+        self.g[v].push(u);
+    }
+
+    fn remove_edge(&mut self, u: usize, v: usize) {
+        self.g[u].retain(|x| *x != v);
+        self.g[v].retain(|x| *x != u);
+        if self.mat[u] == v as i32 {
+            self.mat[u] = -1;
+            self.mat[v] = -1;
+        }
+    }
+
+    fn remove_vertex(&mut self, v: usize) {
+        for idx in 0..self.g[v].len() {
+            let u = self.g[v][idx];
+            self.g[u].retain(|x| *x != v);
+        }
+        self.g[v].clear();
+        if self.mat[v] != -1 {
+            let u = self.mat[v] as usize;
+            self.mat[u] = -1;
+            self.mat[v] = -1;
+        }
+    }
+
+    fn warm_start(&mut self, matching: &[i32]) {
+        for (v, &pair) in matching.iter().enumerate().take(self.g.len()) {
+            if pair < 0 || pair as usize >= self.g.len() { continue; }
+            let u = pair as usize;
+            if self.mat[v] != -1 || self.mat[u] != -1 || !self.g[v].contains(&u) { continue; }
+            self.mat[v] = u as i32;
+            self.mat[u] = v as i32;
+        }
+    }
+
+    fn augment(&mut self) -> usize {
+        // every round starts with nothing visited, so paths through vertices
+        // visited in earlier calls are found too
+        let mut changed = true;
+        while changed {
+            changed = false;
+            self.vis.fill(false);
+            for v in 0..self.g.len() {
+                if self.mat[v] == -1 && self.dfs(v) { changed = true; }
+            }
+        }
+        self.mat.iter().filter(|x| **x != -1).count()/2
+    }
 }
 
 #[cfg(test)]
@@ -133,6 +190,33 @@ mod tests {
         assert_eq!(got, 8);
     }
 
+    #[test]
+    fn incremental() {
+        let mut matcher = TurboMatching::new();
+        matcher.add_edge(0, 2);
+        assert_eq!(matcher.augment(), 1);
+        matcher.add_edge(1, 2);
+        assert_eq!(matcher.augment(), 1);
+        matcher.add_edge(0, 3);
+        assert_eq!(matcher.augment(), 2);
+        assert_eq!(*matcher.get_matching(), vec![3, 2, 1, 0]);
+
+        matcher.remove_vertex(2);
+        assert_eq!(matcher.augment(), 1);
+        assert_eq!(*matcher.get_matching(), vec![3, -1, -1, 0]);
+        matcher.remove_edge(0, 3);
+        assert_eq!(matcher.augment(), 0);
+        assert_eq!(*matcher.get_matching(), vec![-1, -1, -1, -1]);
+
+        let mut other = TurboMatching::new();
+        other.add_edge(0, 2);
+        other.add_edge(0, 3);
+        other.add_edge(1, 3);
+        other.warm_start(&[3, -1, -1, 0]);
+        assert_eq!(*other.get_matching(), vec![3, -1, -1, 0]);
+        assert_eq!(other.augment(), 2);
+    }
+
     #[test]
     fn large_random() {
         let maxn = 250_000;